@@ -0,0 +1,62 @@
+//! The escape sequences lcp draws with, which every terminal it runs in
+//! understands whatever backend writes them.
+
+use std::fmt;
+
+pub(crate) const CLEAR_SCREEN: &str = "\x1b[2J";
+pub(crate) const CLEAR_LINE: &str = "\x1b[2K";
+pub(crate) const HIDE_CURSOR: &str = "\x1b[?25l";
+pub(crate) const SHOW_CURSOR: &str = "\x1b[?25h";
+
+pub(crate) const BOLD: &str = "\x1b[1m";
+/// Ends bold text. The `21m` meant for it is double underline on most
+/// terminals, while `22m` ends both bold and faint text.
+pub(crate) const NO_BOLD: &str = "\x1b[22m";
+pub(crate) const UNDERLINE: &str = "\x1b[4m";
+pub(crate) const NO_UNDERLINE: &str = "\x1b[24m";
+
+/// A color of the 256 color palette, or a 24-bit one.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Color {
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+    /// The terminal's own color.
+    Reset,
+}
+
+impl Color {
+    /// The bright white of the palette.
+    pub(crate) const LIGHT_WHITE: Color = Color::Indexed(15);
+}
+
+/// The sequence setting the color of text.
+pub(crate) struct Fg(pub(crate) Color);
+
+/// The sequence setting the color behind text.
+pub(crate) struct Bg(pub(crate) Color);
+
+impl fmt::Display for Fg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_color(f, 38, self.0)
+    }
+}
+
+impl fmt::Display for Bg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_color(f, 48, self.0)
+    }
+}
+
+/// `38` and `48` set the foreground and background, `39` and `49` reset them.
+fn write_color(f: &mut fmt::Formatter<'_>, base: u8, color: Color) -> fmt::Result {
+    match color {
+        Color::Indexed(index) => write!(f, "\x1b[{base};5;{index}m"),
+        Color::Rgb(r, g, b) => write!(f, "\x1b[{base};2;{r};{g};{b}m"),
+        Color::Reset => write!(f, "\x1b[{}m", base + 1),
+    }
+}
+
+/// The sequence moving the cursor to column `x` of row `y`, counting from 0.
+pub(crate) fn goto(x: u16, y: u16) -> String {
+    format!("\x1b[{};{}H", u32::from(y) + 1, u32::from(x) + 1)
+}
@@ -0,0 +1,259 @@
+use regex::Regex;
+
+use crate::clipboard::Backend;
+use crate::document::Tokenizer;
+use crate::editor::LineNumbers;
+use crate::encoding::Encoding;
+use crate::output::OutputFormat;
+
+pub(crate) const USAGE: &str = "\
+Usage: lcp [OPTIONS] [FILE]...
+
+Pick text from the FILEs, or stdin when there are none or for -, and copy it.
+TAB switches between the FILEs.
+
+Input:
+      --tmux[=<PANE>]            Read the scrollback of a tmux pane
+      --history                  Read previously copied text
+      --allow-empty              Exit with 0 when there is nothing to pick from
+      --encoding <auto|utf-8|utf-16le|utf-16be|latin1|windows-1252>
+                                 What the input is encoded in
+
+Tokens:
+      --tokenizer <whitespace|line>
+                                 How rows are split into tokens
+      --delimiter <STRING>       Split rows on STRING
+      --regex <PATTERN>          Make the matches of PATTERN the tokens
+
+Display:
+      --start-line <LINE>        Start on row LINE, counting from 1
+      --start-search <QUERY>     Start searching for QUERY
+      --line-numbers[=absolute|relative]
+      --wrap                     Wrap long rows
+      --header[=<ROWS>]          Pin ROWS rows at the top
+      --no-header                Don't pin a table header
+      --tab-width <COLUMNS>      Put tab stops COLUMNS apart [default: 8]
+
+Output:
+      --output <FILE>            Also write the copied text to FILE, - for stdout
+      --clipboard-backend <auto|system|tmux>
+                                 Where to copy to
+      --no-clipboard             Don't copy to any clipboard
+      --trim                     Trim whitespace around each line
+      --quote                    Shell-quote each line
+      --format <TEMPLATE>        Put each line in place of {}
+      --join <SEPARATOR>         Join lines with SEPARATOR
+      --json                     Output the lines as a JSON array
+      --preset <NAME>            Bind the commands of a config preset
+
+  -h, --help                     Print this help
+  -V, --version                  Print the version
+";
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Source {
+    Stdin,
+    /// The files to switch between, `-` being stdin.
+    Files(Vec<String>),
+    /// Capture a tmux pane. `None` is the pane lcp was started from.
+    Tmux(Option<String>),
+    /// Previously copied text.
+    History,
+}
+
+impl Source {
+    pub(crate) fn as_str(&self) -> String {
+        match self {
+            Source::Stdin => "stdin".to_string(),
+            Source::Files(file_names) => file_names.join(" "),
+            Source::Tmux(None) => "tmux capture-pane".to_string(),
+            Source::Tmux(Some(pane)) => format!("tmux capture-pane -t {pane}"),
+            Source::History => "history".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Args {
+    pub(crate) source: Source,
+    pub(crate) preset: Option<String>,
+    pub(crate) output: OutputFormat,
+    pub(crate) line_numbers: LineNumbers,
+    pub(crate) wrap: bool,
+    /// How many rows to pin at the top, instead of detecting a table header.
+    pub(crate) header: Option<usize>,
+    /// Exit successfully without a word when the input is blank.
+    pub(crate) allow_empty: bool,
+    pub(crate) tokenizer: Option<Tokenizer>,
+    /// What the input is encoded in, instead of detecting it.
+    pub(crate) encoding: Option<Encoding>,
+    /// The row to start on, counting from 1.
+    pub(crate) start_line: Option<usize>,
+    pub(crate) start_search: Option<String>,
+    /// How many columns apart tab stops are.
+    pub(crate) tab_width: Option<usize>,
+    /// Where else to write copied text, `-` being stdout.
+    pub(crate) output_file: Option<String>,
+    pub(crate) clipboard: Backend,
+    pub(crate) help: bool,
+    pub(crate) version: bool,
+}
+
+/// Only one of them can be read from.
+const SOURCES: &str = "Files, --tmux and --history can't be used together";
+
+impl Args {
+    pub(crate) fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut source = Source::Stdin;
+        let mut preset = None;
+        let mut output = OutputFormat::default();
+        let mut line_numbers = LineNumbers::Off;
+        let mut wrap = false;
+        let mut header = None;
+        let mut allow_empty = false;
+        let mut tokenizer = None;
+        let mut encoding = None;
+        let mut start_line = None;
+        let mut start_search = None;
+        let mut tab_width = None;
+        let mut output_file = None;
+        let mut clipboard = Backend::default();
+        let mut help = false;
+        let mut version = false;
+        let mut files = Vec::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            // Options take their value either as `--name=value` or `--name value`.
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("{name} needs a value"))
+            };
+            match name {
+                "--tmux" if source == Source::History => return Err(SOURCES.to_string()),
+                "--history" if matches!(source, Source::Tmux(_)) => {
+                    return Err(SOURCES.to_string());
+                }
+                "--tmux" => source = Source::Tmux(inline_value.clone()),
+                "--history" => source = Source::History,
+                "--preset" => preset = Some(value()?),
+                "--join" => output.separator = Some(value()?),
+                "--trim" => output.trim = true,
+                "--quote" => output.quote = true,
+                "--json" => output.json = true,
+                "--format" => output.template = Some(value()?),
+                "--wrap" => wrap = true,
+                "--header" => {
+                    header = match inline_value.as_deref() {
+                        None => Some(1),
+                        Some(rows) => Some(
+                            rows.parse()
+                                .map_err(|_| format!("Invalid number of header rows: {rows}"))?,
+                        ),
+                    }
+                }
+                "--no-header" => header = Some(0),
+                "--allow-empty" => allow_empty = true,
+                "--line-numbers" => {
+                    line_numbers = match inline_value.as_deref() {
+                        None | Some("absolute") => LineNumbers::Absolute,
+                        Some("relative") => LineNumbers::Relative,
+                        Some(other) => return Err(format!("Unknown line numbers: {other}")),
+                    }
+                }
+                "--tokenizer" => {
+                    tokenizer = match value()?.as_str() {
+                        "whitespace" => Some(Tokenizer::Whitespace),
+                        "line" => Some(Tokenizer::Line),
+                        other => return Err(format!("Unknown tokenizer: {other}")),
+                    }
+                }
+                "--delimiter" => match value()? {
+                    delimiter if delimiter.is_empty() => {
+                        return Err("The delimiter can't be empty".to_string());
+                    }
+                    delimiter => tokenizer = Some(Tokenizer::String(delimiter)),
+                },
+                "--regex" => {
+                    let pattern = value()?;
+                    let regex =
+                        Regex::new(&pattern).map_err(|_| format!("Invalid regex: {pattern}"))?;
+                    tokenizer = Some(Tokenizer::Regex(regex));
+                }
+                "--encoding" => {
+                    encoding = match value()?.as_str() {
+                        "auto" => None,
+                        name => Some(
+                            Encoding::parse(name)
+                                .ok_or_else(|| format!("Unknown encoding: {name}"))?,
+                        ),
+                    }
+                }
+                "--start-line" => {
+                    let line = value()?;
+                    start_line = match line.parse() {
+                        Ok(0) | Err(_) => return Err(format!("Invalid start line: {line}")),
+                        Ok(line) => Some(line),
+                    }
+                }
+                "--start-search" => start_search = Some(value()?),
+                "--tab-width" => {
+                    let columns = value()?;
+                    tab_width = match columns.parse() {
+                        Ok(0) | Err(_) => return Err(format!("Invalid tab width: {columns}")),
+                        Ok(columns) => Some(columns),
+                    }
+                }
+                "--output" => output_file = Some(value()?),
+                "--clipboard-backend" => {
+                    clipboard = match value()?.as_str() {
+                        "auto" => Backend::Auto,
+                        "system" => Backend::System,
+                        "tmux" => Backend::Tmux,
+                        other => return Err(format!("Unknown clipboard backend: {other}")),
+                    }
+                }
+                "--no-clipboard" => clipboard = Backend::None,
+                "-h" | "--help" => help = true,
+                "-V" | "--version" => version = true,
+                _ if name.starts_with('-') && name != "-" => {
+                    return Err(format!("Unknown option: {arg}"));
+                }
+                _ => files.push(arg.clone()),
+            }
+        }
+        if !files.is_empty() && source != Source::Stdin {
+            return Err(SOURCES.to_string());
+        }
+        if !files.is_empty() && files != ["-"] {
+            source = Source::Files(files);
+        }
+        Ok(Self {
+            source,
+            preset,
+            output,
+            line_numbers,
+            wrap,
+            header,
+            allow_empty,
+            tokenizer,
+            encoding,
+            start_line,
+            start_search,
+            tab_width,
+            output_file,
+            clipboard,
+            help,
+            version,
+        })
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/test_args.rs"]
+mod tests;
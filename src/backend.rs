@@ -0,0 +1,348 @@
+#[cfg(any(feature = "termion", feature = "crossterm"))]
+use std::fs;
+use std::io;
+#[cfg(any(feature = "termion", feature = "crossterm"))]
+use std::io::Write;
+#[cfg(test)]
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+#[cfg(all(feature = "termion", not(feature = "crossterm")))]
+use termion::input::TermRead;
+#[cfg(all(feature = "termion", not(feature = "crossterm")))]
+use termion::raw::{IntoRawMode, RawTerminal};
+
+use crate::ansi;
+
+#[cfg(not(any(feature = "termion", feature = "crossterm")))]
+compile_error!("lcp draws with termion or crossterm: enable one of their features");
+// With both features, as with `--features crossterm` on top of the default
+// termion, crossterm is used and termion left out.
+
+/// A key pressed, whichever library read it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Key {
+    Char(char),
+    Ctrl(char),
+    Alt(char),
+    Backspace,
+    Delete,
+    Insert,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    /// Shift-TAB.
+    BackTab,
+    F(u8),
+    Esc,
+}
+
+/// What the editor draws on and reads keys from.
+///
+/// Drawing is buffered until `flush`, so that a frame goes out in one write
+/// instead of flickering in piece by piece. Text may have color and style
+/// sequences in it, which every terminal lcp runs in understands, so the
+/// backends write the ones of `ansi` and leave the rest to their library.
+pub(crate) trait Backend {
+    /// The columns and rows of the screen.
+    fn size(&self) -> io::Result<(u16, u16)>;
+    /// Moves the cursor to column `x` of row `y`, counting from 0.
+    fn goto(&mut self, x: u16, y: u16) -> io::Result<()>;
+    fn clear_screen(&mut self) -> io::Result<()>;
+    fn clear_line(&mut self) -> io::Result<()>;
+    fn show_cursor(&mut self, show: bool) -> io::Result<()>;
+    fn print(&mut self, text: &str) -> io::Result<()>;
+    fn flush(&mut self) -> io::Result<()>;
+    /// Waits for a key to be pressed.
+    fn read_key(&mut self) -> io::Result<Key>;
+}
+
+/// The terminal, through termion.
+#[cfg(all(feature = "termion", not(feature = "crossterm")))]
+pub(crate) struct Termion {
+    // The tty rather than stdout, which may be where the copied text goes.
+    tty: fs::File,
+    _raw: RawTerminal<fs::File>,
+    buffer: Vec<u8>,
+}
+
+#[cfg(all(feature = "termion", not(feature = "crossterm")))]
+impl Termion {
+    pub(crate) fn new() -> io::Result<Self> {
+        Ok(Self {
+            tty: termion::get_tty()?,
+            _raw: termion::get_tty()?.into_raw_mode()?,
+            buffer: Vec::new(),
+        })
+    }
+}
+
+#[cfg(all(feature = "termion", not(feature = "crossterm")))]
+impl Backend for Termion {
+    fn size(&self) -> io::Result<(u16, u16)> {
+        termion::terminal_size()
+    }
+
+    fn goto(&mut self, x: u16, y: u16) -> io::Result<()> {
+        self.print(&ansi::goto(x, y))
+    }
+
+    fn clear_screen(&mut self) -> io::Result<()> {
+        self.print(ansi::CLEAR_SCREEN)
+    }
+
+    fn clear_line(&mut self) -> io::Result<()> {
+        self.print(ansi::CLEAR_LINE)
+    }
+
+    fn show_cursor(&mut self, show: bool) -> io::Result<()> {
+        self.print(if show {
+            ansi::SHOW_CURSOR
+        } else {
+            ansi::HIDE_CURSOR
+        })
+    }
+
+    fn print(&mut self, text: &str) -> io::Result<()> {
+        self.buffer.write_all(text.as_bytes())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.tty.write_all(&self.buffer)?;
+        self.buffer.clear();
+        self.tty.flush()
+    }
+
+    fn read_key(&mut self) -> io::Result<Key> {
+        loop {
+            if let Some(key) = self.tty.try_clone()?.keys().next()
+                && let Some(key) = termion_key(key?)
+            {
+                return Ok(key);
+            }
+        }
+    }
+}
+
+/// The key the editor knows a termion key as.
+#[cfg(all(feature = "termion", not(feature = "crossterm")))]
+fn termion_key(key: termion::event::Key) -> Option<Key> {
+    use termion::event::Key as TermionKey;
+    Some(match key {
+        TermionKey::Char(c) => Key::Char(c),
+        TermionKey::Ctrl(c) => Key::Ctrl(c),
+        TermionKey::Alt(c) => Key::Alt(c),
+        TermionKey::Backspace => Key::Backspace,
+        TermionKey::Delete => Key::Delete,
+        TermionKey::Insert => Key::Insert,
+        TermionKey::Left => Key::Left,
+        TermionKey::Right => Key::Right,
+        TermionKey::Up => Key::Up,
+        TermionKey::Down => Key::Down,
+        TermionKey::Home => Key::Home,
+        TermionKey::End => Key::End,
+        TermionKey::PageUp => Key::PageUp,
+        TermionKey::PageDown => Key::PageDown,
+        TermionKey::BackTab => Key::BackTab,
+        TermionKey::F(n) => Key::F(n),
+        TermionKey::Esc => Key::Esc,
+        _ => return None,
+    })
+}
+
+/// The terminal, through crossterm.
+#[cfg(feature = "crossterm")]
+pub(crate) struct Crossterm {
+    tty: fs::File,
+    buffer: Vec<u8>,
+}
+
+#[cfg(feature = "crossterm")]
+impl Crossterm {
+    pub(crate) fn new() -> io::Result<Self> {
+        use std::io::IsTerminal;
+        let tty = fs::OpenOptions::new().write(true).open("/dev/tty")?;
+        if !tty.is_terminal() {
+            return Err(io::Error::other("/dev/tty is not a terminal"));
+        }
+        // Raw mode is turned off again when this is dropped, even if turning
+        // it on fails halfway.
+        let backend = Self {
+            tty,
+            buffer: Vec::new(),
+        };
+        crossterm::terminal::enable_raw_mode()?;
+        Ok(backend)
+    }
+}
+
+#[cfg(feature = "crossterm")]
+impl Drop for Crossterm {
+    fn drop(&mut self) {
+        let _ = crossterm::terminal::disable_raw_mode();
+    }
+}
+
+#[cfg(feature = "crossterm")]
+impl Backend for Crossterm {
+    fn size(&self) -> io::Result<(u16, u16)> {
+        crossterm::terminal::size()
+    }
+
+    fn goto(&mut self, x: u16, y: u16) -> io::Result<()> {
+        self.print(&ansi::goto(x, y))
+    }
+
+    fn clear_screen(&mut self) -> io::Result<()> {
+        self.print(ansi::CLEAR_SCREEN)
+    }
+
+    fn clear_line(&mut self) -> io::Result<()> {
+        self.print(ansi::CLEAR_LINE)
+    }
+
+    fn show_cursor(&mut self, show: bool) -> io::Result<()> {
+        self.print(if show {
+            ansi::SHOW_CURSOR
+        } else {
+            ansi::HIDE_CURSOR
+        })
+    }
+
+    fn print(&mut self, text: &str) -> io::Result<()> {
+        self.buffer.write_all(text.as_bytes())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.tty.write_all(&self.buffer)?;
+        self.buffer.clear();
+        self.tty.flush()
+    }
+
+    fn read_key(&mut self) -> io::Result<Key> {
+        use crossterm::event::{self, Event, KeyEventKind};
+        loop {
+            if let Event::Key(key) = event::read()?
+                && key.kind != KeyEventKind::Release
+                && let Some(key) = crossterm_key(key)
+            {
+                return Ok(key);
+            }
+        }
+    }
+}
+
+/// The key the editor knows a crossterm key as.
+#[cfg(feature = "crossterm")]
+fn crossterm_key(key: crossterm::event::KeyEvent) -> Option<Key> {
+    use crossterm::event::{KeyCode, KeyModifiers};
+    Some(match key.code {
+        KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) => Key::Ctrl(c),
+        KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::ALT) => Key::Alt(c),
+        KeyCode::Char(c) => Key::Char(c),
+        KeyCode::Enter => Key::Char('\n'),
+        KeyCode::Tab => Key::Char('\t'),
+        KeyCode::BackTab => Key::BackTab,
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Delete => Key::Delete,
+        KeyCode::Insert => Key::Insert,
+        KeyCode::Left => Key::Left,
+        KeyCode::Right => Key::Right,
+        KeyCode::Up => Key::Up,
+        KeyCode::Down => Key::Down,
+        KeyCode::Home => Key::Home,
+        KeyCode::End => Key::End,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        KeyCode::F(n) => Key::F(n),
+        KeyCode::Esc => Key::Esc,
+        _ => return None,
+    })
+}
+
+/// A screen in memory, for tests. Keys are read from a queue, and what is
+/// drawn is kept as the escape sequences a terminal would get.
+#[cfg(test)]
+pub(crate) struct Memory {
+    size: (u16, u16),
+    keys: VecDeque<Key>,
+    buffer: String,
+    output: Rc<RefCell<String>>,
+}
+
+#[cfg(test)]
+impl Memory {
+    pub(crate) fn new(width: u16, height: u16) -> Self {
+        Self {
+            size: (width, height),
+            keys: VecDeque::new(),
+            buffer: String::new(),
+            output: Rc::default(),
+        }
+    }
+
+    /// Sets the keys to read, after which `Esc` is read.
+    pub(crate) fn with_keys(mut self, keys: impl IntoIterator<Item = Key>) -> Self {
+        self.keys = keys.into_iter().collect();
+        self
+    }
+
+    /// What has been flushed, which stays readable once the backend is
+    /// handed over.
+    pub(crate) fn output(&self) -> Rc<RefCell<String>> {
+        Rc::clone(&self.output)
+    }
+}
+
+#[cfg(test)]
+impl Backend for Memory {
+    fn size(&self) -> io::Result<(u16, u16)> {
+        Ok(self.size)
+    }
+
+    fn goto(&mut self, x: u16, y: u16) -> io::Result<()> {
+        self.buffer.push_str(&ansi::goto(x, y));
+        Ok(())
+    }
+
+    fn clear_screen(&mut self) -> io::Result<()> {
+        self.buffer.push_str(ansi::CLEAR_SCREEN);
+        Ok(())
+    }
+
+    fn clear_line(&mut self) -> io::Result<()> {
+        self.buffer.push_str(ansi::CLEAR_LINE);
+        Ok(())
+    }
+
+    fn show_cursor(&mut self, show: bool) -> io::Result<()> {
+        self.print(if show {
+            ansi::SHOW_CURSOR
+        } else {
+            ansi::HIDE_CURSOR
+        })
+    }
+
+    fn print(&mut self, text: &str) -> io::Result<()> {
+        self.buffer.push_str(text);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.borrow_mut().push_str(&self.buffer);
+        self.buffer.clear();
+        Ok(())
+    }
+
+    fn read_key(&mut self) -> io::Result<Key> {
+        Ok(self.keys.pop_front().unwrap_or(Key::Esc))
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/test_backend.rs"]
+mod tests;
@@ -0,0 +1,28 @@
+use crate::tmux::Tmux;
+
+/// Which clipboards copied text is sent to.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum Backend {
+    /// The system clipboard, and the tmux buffer when reading a tmux pane.
+    #[default]
+    Auto,
+    System,
+    Tmux,
+    /// Nowhere, e.g. when the text is only wanted on stdout.
+    None,
+}
+
+/// A place copied text can be sent to.
+pub enum Clipboard {
+    System(arboard::Clipboard),
+    Tmux(Tmux),
+}
+
+impl Clipboard {
+    pub(crate) fn set_text(&mut self, text: &str) -> Result<(), String> {
+        match self {
+            Clipboard::System(clipboard) => clipboard.set_text(text).map_err(|e| e.to_string()),
+            Clipboard::Tmux(tmux) => tmux.set_buffer(text).map_err(|e| e.to_string()),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::fs::File;
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Stdio};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Action {
+    /// Replace lcp with the command.
+    Exec,
+    /// Open the output of the command as a new document.
+    View,
+}
+
+/// A shell command bound to a key, with `{}` standing in for the selection.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct CommandTemplate {
+    pub(crate) key: char,
+    pub(crate) action: Action,
+    pub(crate) template: String,
+}
+
+impl CommandTemplate {
+    /// The template with every `{}` replaced by the quoted selection.
+    pub(crate) fn command_line(&self, selection: &str) -> String {
+        self.template.replace("{}", &shell_quote(selection))
+    }
+}
+
+/// Quotes `s` as a single shell word.
+pub(crate) fn shell_quote(s: &str) -> String {
+    if !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c))
+    {
+        return s.to_string();
+    }
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Runs `command_line` and returns what it printed on stdout and stderr.
+pub(crate) fn output(command_line: &str) -> io::Result<Vec<u8>> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command_line)
+        .stdin(Stdio::null())
+        .output()?;
+    let mut result = output.stdout;
+    result.extend(output.stderr);
+    Ok(result)
+}
+
+/// Replaces the current process with `command_line`. Only returns on failure.
+pub(crate) fn exec(command_line: &str) -> io::Error {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(command_line);
+    // Our stdin is usually the pipe we read the document from.
+    if let Ok(tty) = File::open("/dev/tty") {
+        command.stdin(tty);
+    }
+    command.exec()
+}
+
+#[cfg(test)]
+#[path = "tests/test_command.rs"]
+mod tests;
@@ -0,0 +1,46 @@
+use std::io::{self, BufRead, BufReader};
+
+/// A compression format lcp reads through, like that of rotated logs.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+    Xz,
+}
+
+impl Compression {
+    /// The format of data starting with `header`, by its magic number.
+    pub(crate) fn detect(header: &[u8]) -> Option<Self> {
+        match header {
+            [0x1f, 0x8b, ..] => Some(Compression::Gzip),
+            [0x28, 0xb5, 0x2f, 0xfd, ..] => Some(Compression::Zstd),
+            [b'B', b'Z', b'h', ..] => Some(Compression::Bzip2),
+            [0xfd, b'7', b'z', b'X', b'Z', 0x00, ..] => Some(Compression::Xz),
+            _ => None,
+        }
+    }
+}
+
+/// Reads `input` decompressed when it is in one of the known formats, and as
+/// is otherwise.
+pub(crate) fn decompress<'a>(mut input: impl BufRead + 'a) -> io::Result<Box<dyn BufRead + 'a>> {
+    // Concatenated streams, as left by `cat a.gz b.gz`, are read as one.
+    Ok(match Compression::detect(input.fill_buf()?) {
+        None => Box::new(input),
+        Some(Compression::Gzip) => {
+            Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(input)))
+        }
+        Some(Compression::Zstd) => Box::new(BufReader::new(zstd::Decoder::with_buffer(input)?)),
+        Some(Compression::Bzip2) => {
+            Box::new(BufReader::new(bzip2::bufread::MultiBzDecoder::new(input)))
+        }
+        Some(Compression::Xz) => Box::new(BufReader::new(
+            liblzma::bufread::XzDecoder::new_multi_decoder(input),
+        )),
+    })
+}
+
+#[cfg(test)]
+#[path = "tests/test_compression.rs"]
+mod tests;
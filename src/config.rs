@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::command::{Action, CommandTemplate};
+
+/// Settings read from `$XDG_CONFIG_HOME/lcp/config`.
+///
+/// Settings before the first `[preset]` header apply to every preset.
+///
+/// ```text
+/// # Show the raw yaml of anything
+/// y = view kubectl get -o yaml {}
+///
+/// [k8s]
+/// l = exec kubectl logs -f {}
+/// d = view kubectl describe pod {}
+/// ```
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Config {
+    global: Preset,
+    presets: HashMap<String, Preset>,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct Preset {
+    pub(crate) commands: Vec<CommandTemplate>,
+}
+
+impl Preset {
+    fn merge(&mut self, other: &Preset) {
+        for command in &other.commands {
+            self.commands.retain(|c| c.key != command.key);
+            self.commands.push(command.clone());
+        }
+    }
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/lcp/config`, falling back to `~/.config`.
+    pub(crate) fn default_path() -> Option<PathBuf> {
+        let config_home = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(config_home.join("lcp").join("config"))
+    }
+
+    /// Reads the config at the default path. A missing file is an empty config.
+    pub(crate) fn load() -> Result<Self, String> {
+        let Some(path) = Self::default_path() else {
+            return Ok(Self::default());
+        };
+        match fs::read_to_string(&path) {
+            Ok(contents) => Self::parse(&contents).map_err(|e| format!("{}: {e}", path.display())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("{}: {e}", path.display())),
+        }
+    }
+
+    pub(crate) fn parse(contents: &str) -> Result<Self, String> {
+        let mut config = Self::default();
+        let mut section: Option<String> = None;
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let err = |message: &str| format!("line {}: {message}", index + 1);
+            if let Some(name) = line.strip_prefix('[') {
+                let name = name
+                    .strip_suffix(']')
+                    .ok_or_else(|| err("expected ']'"))?
+                    .trim();
+                config.presets.entry(name.to_string()).or_default();
+                section = Some(name.to_string());
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| err("expected 'key = value'"))?;
+            let command = parse_command(key.trim(), value.trim()).map_err(|e| err(&e))?;
+            let preset = match &section {
+                None => &mut config.global,
+                Some(name) => config.presets.entry(name.clone()).or_default(),
+            };
+            preset.merge(&Preset {
+                commands: vec![command],
+            });
+        }
+        Ok(config)
+    }
+
+    /// The settings of preset `name` on top of the global ones.
+    pub(crate) fn preset(&self, name: Option<&str>) -> Result<Preset, String> {
+        let mut preset = self.global.clone();
+        if let Some(name) = name {
+            let named = self
+                .presets
+                .get(name)
+                .ok_or_else(|| format!("Unknown preset: {name}"))?;
+            preset.merge(named);
+        }
+        Ok(preset)
+    }
+}
+
+fn parse_command(key: &str, value: &str) -> Result<CommandTemplate, String> {
+    let mut chars = key.chars();
+    let (Some(key), None) = (chars.next(), chars.next()) else {
+        return Err(format!(
+            "command keys must be a single character, got '{key}'"
+        ));
+    };
+    let (action, template) = value.split_once(' ').unwrap_or((value, ""));
+    let action = match action {
+        "exec" => Action::Exec,
+        "view" => Action::View,
+        _ => return Err(format!("expected 'exec' or 'view', got '{action}'")),
+    };
+    let template = template.trim();
+    if template.is_empty() {
+        return Err("missing command".to_string());
+    }
+    Ok(CommandTemplate {
+        key,
+        action,
+        template: template.to_string(),
+    })
+}
+
+#[cfg(test)]
+#[path = "tests/test_config.rs"]
+mod tests;
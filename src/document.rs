@@ -1,33 +1,86 @@
-use std::io::BufRead;
+use std::borrow::Cow;
+use std::cell::{Cell, OnceCell, RefCell};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::rc::Rc;
+
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::Position;
 use crate::Row;
 use crate::SearchDirection;
-use crate::highlighting::{HighlightedText, TextMode};
-use crate::row::{mk_tokens, switch_start_end};
+use crate::encoding::Encoding;
+use crate::highlighting::{HighlightedText, Selection, TextMode};
+use crate::lines::Lines;
+use crate::row::{TAB_WIDTH, overstrike, switch_start_end};
+use crate::tree::Tree;
+
+/// How many rows are kept once made, a few screens' worth.
+const CACHED_ROWS: usize = 4096;
+
+/// How many rows at the top are measured when opening a document, for the
+/// longest row to be known on the first screens.
+const MEASURED_ROWS: usize = 1024;
+
+/// The largest document parsed as YAML or JSON, since parsing needs all of
+/// its lines in memory.
+const MAX_TREE_SIZE: usize = 16 * 1024 * 1024;
 
 #[derive(Clone, Debug)]
 pub enum Tokenizer {
     Whitespace,
     String(String),
+    /// Each line is a single token.
+    Line,
+    /// The tokens are the matches of the regex.
+    Regex(Regex),
 }
 
+impl PartialEq for Tokenizer {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Tokenizer::Whitespace, Tokenizer::Whitespace) | (Tokenizer::Line, Tokenizer::Line) => {
+                true
+            }
+            (Tokenizer::String(a), Tokenizer::String(b)) => a == b,
+            (Tokenizer::Regex(a), Tokenizer::Regex(b)) => a.as_str() == b.as_str(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Tokenizer {}
+
 impl Tokenizer {
     pub(crate) fn as_str(&self) -> String {
         match self {
             Tokenizer::Whitespace => "whitespace (default)".to_string(),
             Tokenizer::String(s) => format!("'{s}'"),
+            Tokenizer::Line => "line".to_string(),
+            Tokenizer::Regex(regex) => format!("/{}/", regex.as_str()),
         }
     }
 }
 
+/// The text being picked from.
+///
+/// Rows are made from the lines of the text when they are first needed, so
+/// that huge files open right away.
 #[derive(Debug)]
 pub struct Document {
-    rows: Vec<Row>,
+    lines: Lines,
+    rows: RefCell<HashMap<usize, Rc<Row>>>,
     // In visual block mode, we consider the length of each row
-    // to be equivalent to the longest row.
-    longest_row: usize,
+    // to be equivalent to the longest row, of those made so far.
+    longest: Cell<usize>,
     tokenizer: Tokenizer,
+    encoding: Encoding,
+    tab_width: usize,
+    highlighted: Option<HighlightedText>,
+    // Parsed on first use, as most documents aren't YAML or JSON.
+    tree: OnceCell<Option<Tree>>,
 }
 
 impl Document {
@@ -36,130 +89,346 @@ impl Document {
     }
 
     pub(crate) fn longest_row(&self) -> usize {
-        self.longest_row
+        self.longest.get().saturating_sub(1)
     }
 
     pub(crate) fn update_tokenizer(&mut self, tokenizer: Tokenizer) {
-        for row in self.rows.iter_mut() {
-            row.tokens = mk_tokens(&row.string, &tokenizer)
-        }
         self.tokenizer = tokenizer;
+        self.rows.get_mut().clear();
+    }
+
+    /// Sets how many columns apart the tab stops of the rows are.
+    pub(crate) fn set_tab_width(&mut self, tab_width: usize) {
+        self.tab_width = tab_width;
+        self.rows.get_mut().clear();
+    }
+
+    pub(crate) fn new(input: impl Read) -> io::Result<Self> {
+        Ok(Self::from_lines(Lines::read(input)?))
+    }
+
+    /// Maps `file` into memory, which leaves reading it to the rows shown.
+    pub(crate) fn open(file: &File) -> io::Result<Self> {
+        Ok(Self::from_lines(Lines::map(file)?))
+    }
+
+    fn from_lines(lines: Lines) -> Self {
+        let document = Self {
+            lines,
+            rows: RefCell::new(HashMap::new()),
+            longest: Cell::new(0),
+            tokenizer: Tokenizer::Whitespace,
+            encoding: Encoding::Utf8,
+            tab_width: TAB_WIDTH,
+            highlighted: None,
+            tree: OnceCell::new(),
+        };
+        document.measure();
+        document
+    }
+
+    fn measure(&self) {
+        let longest = (0..MEASURED_ROWS)
+            .take_while(|&index| self.has_row(index))
+            .map(|index| self.text(index).graphemes(true).count())
+            .max();
+        self.longest.set(longest.unwrap_or_default());
     }
-    pub(crate) fn new(input: impl BufRead) -> Result<Self, std::io::Error> {
-        let tokenizer = Tokenizer::Whitespace;
-        let mut rows = Vec::new();
-        let mut longest_row = 0;
 
-        for value in input.lines() {
-            let row = Row::new(value?.as_str().trim_end(), &tokenizer);
-            longest_row = std::cmp::max(longest_row, row.len);
-            rows.push(row);
+    pub(crate) fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Reads the text as `encoding` rather than UTF-8. Wide encodings are
+    /// transcoded to UTF-8 whole, as their line breaks aren't `\n` bytes.
+    pub(crate) fn set_encoding(&mut self, encoding: Encoding) {
+        if encoding.is_wide() {
+            self.lines = Lines::from_bytes(encoding.transcode(self.bytes()));
         }
-        for row in rows.iter_mut() {
-            row.whitespace_pad(longest_row);
+        self.encoding = encoding;
+        self.rows.get_mut().clear();
+        self.tree = OnceCell::new();
+        self.measure();
+    }
+
+    /// The bytes of the whole document.
+    pub(crate) fn bytes(&self) -> &[u8] {
+        self.lines.bytes()
+    }
+
+    /// How many rows at the top are the header of a table, like the
+    /// `NAME  READY  STATUS` of kubectl: upper case words over other rows.
+    pub(crate) fn table_header(&self) -> usize {
+        let first = self.text(0);
+        let words: Vec<&str> = first.split_whitespace().collect();
+        let is_header = self.has_row(1)
+            && words.len() > 1
+            && words.iter().all(|word| {
+                word.chars().any(|c| c.is_uppercase()) && !word.chars().any(char::is_lowercase)
+            });
+        usize::from(is_header)
+    }
+
+    /// The document parsed as YAML or JSON, if it is either and isn't too
+    /// large to parse.
+    pub(crate) fn tree(&self) -> Option<&Tree> {
+        self.tree
+            .get_or_init(|| {
+                if self.is_too_large_for_tree() {
+                    return None;
+                }
+                let lines: Vec<Cow<str>> = (0..self.len()).map(|y| self.text(y)).collect();
+                Tree::parse(lines.iter().map(|line| line.as_ref()))
+            })
+            .as_ref()
+    }
+
+    /// Whether the document is too large to be parsed as YAML or JSON.
+    pub(crate) fn is_too_large_for_tree(&self) -> bool {
+        self.bytes().len() > MAX_TREE_SIZE
+    }
+
+    /// The text of row `index`, as it shows on a terminal, without the
+    /// whitespace at its end.
+    pub(crate) fn text(&self, index: usize) -> Cow<'_, str> {
+        let line = self.line(index);
+        match overstrike(&line) {
+            None => line,
+            Some(cells) => {
+                let text: String = cells.into_iter().map(|(c, _)| c).collect();
+                Cow::Owned(text.trim_end().to_string())
+            }
         }
+    }
 
-        Ok(Self {
-            rows,
-            tokenizer,
-            longest_row: longest_row.saturating_sub(1),
-        })
+    /// Line `index`, decoded, without the whitespace at its end.
+    fn line(&self, index: usize) -> Cow<'_, str> {
+        self.decode(self.lines.get(index).unwrap_or_default())
     }
-    pub(crate) fn row(&self, index: usize) -> &Row {
-        self.rows
-            .get(index)
-            .unwrap_or_else(|| panic!("Expected row at: {index}"))
+
+    /// `line`, decoded, without the whitespace at its end.
+    fn decode<'a>(&self, line: &'a [u8]) -> Cow<'a, str> {
+        match self.encoding.decode(line) {
+            Cow::Borrowed(text) => Cow::Borrowed(text.trim_end()),
+            Cow::Owned(text) => Cow::Owned(text.trim_end().to_string()),
+        }
     }
 
-    pub(crate) fn is_empty(&self) -> bool {
-        self.rows.is_empty()
+    /// Row `index`, if the document has it.
+    pub(crate) fn row(&self, index: usize) -> Option<Rc<Row>> {
+        if !self.has_row(index) {
+            return None;
+        }
+        if let Some(row) = self.rows.borrow().get(&index) {
+            return Some(Rc::clone(row));
+        }
+        let row =
+            Rc::new(Row::new(&self.line(index), &self.tokenizer).with_tab_width(self.tab_width));
+        self.longest.set(self.longest.get().max(row.len));
+        let mut rows = self.rows.borrow_mut();
+        if rows.len() >= CACHED_ROWS {
+            rows.clear();
+        }
+        rows.insert(index, Rc::clone(&row));
+        Some(row)
     }
-    pub(crate) fn len(&self) -> usize {
-        self.rows.len()
+
+    /// Whether there is a row `index`. Unlike `len`, this only looks for the
+    /// rows up to `index`.
+    pub(crate) fn has_row(&self, index: usize) -> bool {
+        // An empty document still has a row for the cursor to be on.
+        index == 0 || self.lines.get(index).is_some()
     }
 
-    pub(crate) fn unhighlight_rows(&mut self) {
-        for row in self.rows.iter_mut() {
-            row.unhighlight();
+    /// `index`, or the last row when the document is shorter.
+    pub(crate) fn clamp(&self, index: usize) -> usize {
+        if self.has_row(index) {
+            index
+        } else {
+            self.len() - 1
         }
     }
 
+    /// Whether there is nothing but whitespace to copy.
+    pub(crate) fn is_blank(&self) -> bool {
+        self.bytes().iter().all(u8::is_ascii_whitespace)
+    }
+
+    /// The number of rows, which has to look for all of them.
+    pub(crate) fn len(&self) -> usize {
+        self.lines.len().max(1)
+    }
+
+    /// The number of rows found so far, and whether that is all of them.
+    pub(crate) fn rows_found(&self) -> (usize, bool) {
+        let (len, complete) = self.lines.found();
+        (len.max(1), complete)
+    }
+
+    /// The next match of `query` from `at`, going round the document once.
+    /// Only the rows with a match are made, so searching a large file goes
+    /// through it in one pass.
     pub(crate) fn find(
         &self,
         query: &str,
         at: &Position,
         direction: SearchDirection,
     ) -> Option<Position> {
-        if at.y >= self.rows.len() {
+        if let Some(position) = self.find_in_row(query, at.y, Some(at.x), direction) {
+            return Some(position);
+        }
+        if !self.has_row(at.y) || query.is_empty() {
             return None;
         }
-        let mut position = Position {
-            x: at.x,
-            y: at.y,
-            longest_row: self.longest_row(),
-        };
+        match direction {
+            SearchDirection::Forward => self
+                .find_in_lines(query, self.lines.forward(at.y + 1), direction)
+                .or_else(|| {
+                    let lines = self.lines.forward(0).take_while(|&(y, _)| y <= at.y);
+                    self.find_in_lines(query, lines, direction)
+                }),
+            SearchDirection::Backward => self
+                .find_in_lines(query, self.lines.backward(at.y), direction)
+                .or_else(|| {
+                    // Going round is the one time all rows have to be found
+                    let lines = self.lines.backward(self.lines.len());
+                    let lines = lines.take_while(|&(y, _)| y >= at.y);
+                    self.find_in_lines(query, lines, direction)
+                }),
+        }
+    }
 
-        for _ in 0..self.len() {
-            if let Some(row) = self.rows.get(position.y) {
-                if let Some(x) = row.find(query, position.x, direction) {
-                    position.x = x;
-                    return Some(position);
+    /// The first match of `query` in `lines`, which only makes the rows of
+    /// the lines it is in.
+    fn find_in_lines<'a>(
+        &self,
+        query: &str,
+        lines: impl Iterator<Item = (usize, &'a [u8])>,
+        direction: SearchDirection,
+    ) -> Option<Position> {
+        lines
+            .filter(|&(_, line)| {
+                let line = self.decode(line);
+                match overstrike(&line) {
+                    None => line.contains(query),
+                    Some(cells) => cells
+                        .iter()
+                        .map(|&(c, _)| c)
+                        .collect::<String>()
+                        .contains(query),
                 }
-                if direction == SearchDirection::Forward {
-                    position.y = position.y.saturating_add(1);
-                    if position.y == self.len() - 1 {
-                        position.y = 0;
-                    }
-                    position.x = 0;
+            })
+            .find_map(|(y, _)| self.find_in_row(query, y, None, direction))
+    }
+
+    /// The match of `query` in row `y` from `x`, or from the row's start or
+    /// end in `direction`.
+    fn find_in_row(
+        &self,
+        query: &str,
+        y: usize,
+        x: Option<usize>,
+        direction: SearchDirection,
+    ) -> Option<Position> {
+        let row = self.row(y)?;
+        let x = x.unwrap_or(match direction {
+            SearchDirection::Forward => 0,
+            SearchDirection::Backward => row.len,
+        });
+        Some(Position {
+            x: row.find(query, x, direction)?,
+            y,
+            longest_row: self.longest_row(),
+        })
+    }
+
+    pub(crate) fn highlight(&mut self, text: &HighlightedText) {
+        self.highlighted = Some(text.clone());
+    }
+
+    /// The columns `start..end` of row `index` that are highlighted.
+    pub(crate) fn highlighted(&self, index: usize) -> (usize, usize) {
+        let Some(text) = &self.highlighted else {
+            return (0, 0);
+        };
+        match text.mode {
+            TextMode::Visual(start_position, selection) => {
+                let (start, end) = switch_start_end(start_position.y, text.position.y);
+                if (start..=end).contains(&index) {
+                    self.visual_range(index, start_position, text.position, selection)
                 } else {
-                    position.y = position.y.saturating_sub(1);
-                    if position.y == 0 {
-                        position.y = self.len() - 1
-                    }
-                    position.x = self.rows[position.y].len;
+                    (0, 0)
                 }
-            } else {
-                return None;
             }
+            _ if index != text.position.y => (0, 0),
+            TextMode::Token => self
+                .row(index)
+                .and_then(|row| row.token(text.position.x).copied())
+                .map_or((0, 0), |token| (token.start, token.start + token.len)),
+            TextMode::Search(Some(len)) => (text.position.x, text.position.x + len),
+            TextMode::Search(None) => (0, 0),
         }
-        None
     }
 
-    pub(crate) fn highlight(&mut self, text: &HighlightedText) {
-        self.unhighlight_rows();
-        if let TextMode::Visual(start_position) = text.mode {
-            let (start, end) = switch_start_end(start_position.y, text.position.y);
-            for row_index in start..end + 1 {
-                if let Some(row) = self.rows.get_mut(row_index) {
-                    row.highlight(text)
-                }
+    /// The columns `start..end` of row `index` covered by a visual selection
+    /// between `start_position` and `end_position`.
+    fn visual_range(
+        &self,
+        index: usize,
+        start_position: Position,
+        end_position: Position,
+        selection: Selection,
+    ) -> (usize, usize) {
+        match selection {
+            Selection::Block => {
+                let (start, end) = switch_start_end(start_position.x, end_position.x);
+                (start, end + 1)
             }
-        } else if let Some(row) = self.rows.get_mut(text.position.y) {
-            row.highlight(text)
+            Selection::Stream => {
+                let (first, last) =
+                    if (start_position.y, start_position.x) <= (end_position.y, end_position.x) {
+                        (start_position, end_position)
+                    } else {
+                        (end_position, start_position)
+                    };
+                let text_len = self.row(index).map_or(0, |row| row.text_len());
+                let start = if index == first.y { first.x } else { 0 };
+                let end = if index == last.y {
+                    last.x + 1
+                } else {
+                    text_len.max(start + 1)
+                };
+                (start, end)
+            }
+            Selection::Line => (0, self.row(index).map_or(0, |row| row.text_len())),
         }
     }
 
     pub(crate) fn get_text(&self, text: &HighlightedText) -> String {
         match text.mode {
             TextMode::Token => {
-                let row = self.row(text.position.y);
+                let Some(row) = self.row(text.position.y) else {
+                    return String::new();
+                };
                 let token = match row.token(text.position.x) {
                     None => return "".to_string(),
                     Some(t) => t,
                 };
-                row.string[token.start..token.start + token.len].to_string()
+                row.slice(token.start, token.start + token.len).to_string()
             }
-            TextMode::Visual(start_pos) => {
-                if start_pos != text.position {
+            TextMode::Visual(start_pos, selection) => {
+                if start_pos != text.position || selection != Selection::Block {
                     let (start, end) = switch_start_end(start_pos.y, text.position.y);
                     let mut lines = Vec::new();
                     for row_index in start..end + 1 {
-                        let row = self.row(row_index);
-                        let (start, end) = switch_start_end(start_pos.x, text.position.x);
-                        // if end + 1 >= row.string.len() {
-                        //     end = row.string.len();
-                        // }
-                        lines.push(&row.string[start..end + 1]);
+                        let Some(row) = self.row(row_index) else {
+                            break;
+                        };
+                        let (start, end) =
+                            self.visual_range(row_index, start_pos, text.position, selection);
+                        // Columns past the end of the row aren't copied
+                        let end = end.min(row.text_len());
+                        lines.push(row.slice(start.min(end), end).to_string());
                     }
                     lines.join("\n")
                 } else {
@@ -167,8 +436,11 @@ impl Document {
                 }
             }
             TextMode::Search(Some(len)) => {
-                let row = self.row(text.position.y);
-                row.string[text.position.x..text.position.x + len].to_string()
+                let Some(row) = self.row(text.position.y) else {
+                    return String::new();
+                };
+                row.slice(text.position.x, text.position.x + len)
+                    .to_string()
             }
             TextMode::Search(None) => String::new(),
         }
@@ -1,24 +1,51 @@
-use arboard::Clipboard;
-use termion::color;
-use termion::event::Key;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 use crate::Document;
 use crate::Terminal;
+use crate::ansi::{Bg, Color, Fg};
+use crate::backend::Key;
+use crate::clipboard::Clipboard;
+use crate::command::{self, Action, CommandTemplate};
 use crate::document::Tokenizer;
-use crate::highlighting::HighlightedText;
+use crate::encoding::Encoding;
+use crate::error::Error;
+use crate::highlighting::{HighlightedText, Selection, TextMode};
+use crate::output::OutputFormat;
+use crate::row::{self, Row, switch_start_end};
+use crate::structure::{self, Span};
 
-const HELP_STRING: &str = "HELP: esc = quit | ENTER = copy | / = find | t = change tokenizer | w = whitespace (default) | v = visual mode";
+const HELP_STRING: &str = "HELP: esc = quit | ENTER = copy | / = find | t = change tokenizer | w = whitespace (default) | v = visual mode | V = visual line mode | + = expand selection | p = YAML/JSON path | # = line numbers | W = wrap | ! = run command | TAB = next file";
 
 const TOKENIZER_STRING: &str = "Enter text to change the tokenizer (default is whitespace): ";
 
 const SEARCH_STRING: &str = "(ESC to cancel | Arrows to navigate): ";
 
-const VISUAL_CURSOR_STRING: &str = "(v = start highlighting | ESC to cancel)";
+const VISUAL_CURSOR_STRING: &str = "(v = start block highlighting | s = start stream highlighting | V = start line highlighting | ESC to cancel)";
 
 const VISUAL_BLOCK_STRING: &str = "(ESC to cancel | ENTER to copy )";
 
-const STATUS_FG_COLOR: color::Rgb = color::Rgb(63, 63, 63);
-const STATUS_BG_COLOR: color::Rgb = color::Rgb(239, 239, 239);
+const STRUCTURE_STRING: &str = "(+ = expand | - = shrink | ENTER to copy | ESC to cancel)";
+
+const TREE_STRING: &str =
+    "(ENTER = copy value | p = copy path | s = copy subtree | arrows to navigate | ESC to cancel)";
+
+const NO_TREE_STRING: &str = "The input isn't YAML or JSON.";
+
+const LARGE_TREE_STRING: &str = "The input is too large to be read as YAML or JSON.";
+
+const COMMAND_STRING: &str = "Run (ESC to cancel): ";
+
+const EX_STRING: &str = "(bn = next file | bp = previous file | ESC to cancel) :";
+
+const NO_COMMANDS_STRING: &str = "No commands are configured for this preset.";
+
+const STATUS_FG_COLOR: Color = Color::Rgb(63, 63, 63);
+const STATUS_BG_COLOR: Color = Color::Rgb(239, 239, 239);
+const GUTTER_FG_COLOR: Color = Color::Rgb(127, 127, 127);
 // const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 #[derive(PartialEq, Eq, Copy, Clone, Debug)]
@@ -31,6 +58,8 @@ pub enum SearchDirection {
 pub enum VisualMode {
     Cursor,
     Block,
+    Stream,
+    Line,
 }
 
 #[derive(Default, Clone, Copy, Debug, Eq, PartialEq)]
@@ -47,12 +76,40 @@ enum CopyStatus {
     Noop,
 }
 
+/// The gutter shown left of the rows.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum LineNumbers {
+    #[default]
+    Off,
+    Absolute,
+    /// The distance to the cursor's row, with the cursor's row numbered as usual.
+    Relative,
+}
+
+impl LineNumbers {
+    fn next(self) -> Self {
+        match self {
+            LineNumbers::Off => LineNumbers::Absolute,
+            LineNumbers::Absolute => LineNumbers::Relative,
+            LineNumbers::Relative => LineNumbers::Off,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 enum InputMode {
     Normal,
     Tokenizer,
     Search(SearchDirection),
     Visual(VisualMode),
+    /// Selecting the structures around a token.
+    Structure,
+    /// Selecting the values of a YAML or JSON document.
+    Tree,
+    /// Waiting for the key of a command. Holds the visual mode to go back to, if any.
+    Command(Option<VisualMode>),
+    /// Typing a command after `:`, like `bn`.
+    Ex,
 }
 
 impl InputMode {
@@ -63,6 +120,22 @@ impl InputMode {
             InputMode::Search(_) => "Search",
             InputMode::Visual(VisualMode::Cursor) => "Visual (Cursor)",
             InputMode::Visual(VisualMode::Block) => "Visual (Block)",
+            InputMode::Visual(VisualMode::Stream) => "Visual (Stream)",
+            InputMode::Visual(VisualMode::Line) => "Visual (Line)",
+            InputMode::Structure => "Structure",
+            InputMode::Tree => "Tree",
+            InputMode::Command(_) => "Command",
+            InputMode::Ex => "Ex",
+        }
+    }
+
+    fn help(&self) -> &'static str {
+        match self {
+            InputMode::Visual(VisualMode::Cursor) => VISUAL_CURSOR_STRING,
+            InputMode::Visual(_) => VISUAL_BLOCK_STRING,
+            InputMode::Structure => STRUCTURE_STRING,
+            InputMode::Tree => TREE_STRING,
+            _ => HELP_STRING,
         }
     }
 }
@@ -71,11 +144,54 @@ impl InputMode {
 enum ShouldQuit {
     No,
     Ye(CopyStatus),
+    /// Quit and replace lcp with this command line.
+    Exec(String),
+}
+
+/// The state of a document we can go back to.
+struct View {
+    document: Document,
+    cursor_position: Position,
+    offset: Position,
+    highlighted_text: HighlightedText,
+    input_mode: InputMode,
+    /// What was typed so far, e.g. the query of a search.
+    prompt_input: String,
+    wrap_offset: usize,
+    header: usize,
+}
+
+impl View {
+    /// A document shown from the top.
+    fn new(document: Document) -> Self {
+        // Visual mode uses the longest row as the width of every row.
+        let cursor_position = Position {
+            longest_row: document.longest_row(),
+            ..Position::default()
+        };
+        Self {
+            cursor_position,
+            offset: Position::default(),
+            highlighted_text: HighlightedText::new_token(cursor_position),
+            input_mode: InputMode::Normal,
+            prompt_input: String::new(),
+            wrap_offset: 0,
+            header: document.table_header(),
+            document,
+        }
+    }
+}
+
+/// A file that isn't shown, with the views opened from it.
+struct Buffer {
+    name: Option<String>,
+    view: View,
+    views: Vec<View>,
 }
 
 pub struct Editor {
     should_quit: ShouldQuit,
-    clipboard: Option<Clipboard>,
+    clipboards: Vec<Clipboard>,
     terminal: Terminal,
     cursor_position: Position,
     offset: Position,
@@ -84,46 +200,179 @@ pub struct Editor {
     highlighted_text: HighlightedText,
     input_mode: InputMode,
     prompt_input: String,
+    /// The selected structure is last, the ones it was expanded from before it.
+    structure: Vec<Span>,
+    /// The selected node of the document's tree.
+    tree_node: usize,
+    commands: Vec<CommandTemplate>,
+    output_format: OutputFormat,
+    line_numbers: LineNumbers,
+    /// Whether long rows continue on the next screen lines.
+    wrap: bool,
+    /// In wrap mode, the first screen line of the row at `offset.y` that is shown.
+    wrap_offset: usize,
+    /// Rows pinned at the top of the screen, like the header of a table.
+    header: usize,
+    /// The rows pinned in every file, in place of the header found in it.
+    header_override: Option<usize>,
+    /// Documents the command output views were opened from, most recent last.
+    views: Vec<View>,
+    /// The name of the file shown, if it is one.
+    name: Option<String>,
+    /// The other files, starting with the next one.
+    buffers: VecDeque<Buffer>,
+    /// Which of the files is shown, counting from 0.
+    buffer: usize,
+    /// What picking a row of the document copies, when that is not the row
+    /// itself, like the entries of the history.
+    copies: Vec<String>,
 }
 
 impl Editor {
-    pub fn run(&mut self) -> std::io::Result<()> {
-        loop {
-            if let Err(error) = self.refresh_screen() {
-                self.die(error)?;
+    pub fn run(&mut self) -> Result<(), Error> {
+        match self.run_loop() {
+            Ok(()) => self.terminal.cursor_show(),
+            Err(e) => {
+                // Leave a usable terminal behind for the error message.
+                let _ = self.terminal.clear_screen();
+                let _ = self.terminal.cursor_show();
+                Err(e)
             }
-            if let ShouldQuit::Ye(_) = &self.should_quit {
-                break;
+        }
+        .map_err(Error::Terminal)
+    }
+
+    fn run_loop(&mut self) -> std::io::Result<()> {
+        loop {
+            self.refresh_screen()?;
+            if self.should_quit != ShouldQuit::No {
+                return Ok(());
             }
             let pressed_key = self.terminal.read_key()?;
-            if let Err(error) = self.process_keypress(pressed_key) {
-                self.die(error)?;
-            }
+            self.process_keypress(pressed_key)?;
         }
-        self.terminal.cursor_show()?;
-        Ok(())
     }
     pub fn new(
         document: Document,
-        clipboard: Option<Clipboard>,
+        clipboards: Vec<Clipboard>,
         terminal: Terminal,
     ) -> Result<Self, std::io::Error> {
-        let highlighted_text = HighlightedText::new_token(Position::default());
+        let View {
+            document,
+            cursor_position,
+            offset,
+            highlighted_text,
+            input_mode,
+            prompt_input,
+            wrap_offset,
+            header,
+        } = View::new(document);
 
         Ok(Self {
             should_quit: ShouldQuit::No,
-            clipboard,
+            clipboards,
             terminal,
             document,
-            cursor_position: Position::default(),
-            offset: Position::default(),
+            cursor_position,
+            offset,
             status_message: HELP_STRING.to_string(),
             highlighted_text,
-            input_mode: InputMode::Normal,
-            prompt_input: "".to_string(),
+            input_mode,
+            prompt_input,
+            structure: Vec::new(),
+            tree_node: 0,
+            commands: Vec::new(),
+            output_format: OutputFormat::default(),
+            line_numbers: LineNumbers::default(),
+            wrap: false,
+            wrap_offset,
+            header,
+            header_override: None,
+            views: Vec::new(),
+            name: None,
+            buffers: VecDeque::new(),
+            buffer: 0,
+            copies: Vec::new(),
         })
     }
 
+    /// Names the file shown.
+    pub(crate) fn set_name(&mut self, name: String) {
+        self.name = Some(name);
+    }
+
+    /// Adds a file to switch to, after the others.
+    pub(crate) fn add_buffer(&mut self, name: String, document: Document) {
+        let mut view = View::new(document);
+        if let Some(header) = self.header_override {
+            view.header = header;
+        }
+        let buffer = Buffer {
+            name: Some(name),
+            view,
+            views: Vec::new(),
+        };
+        // The ones before the shown file are at the back.
+        let index = self.buffers.len() - self.buffer;
+        self.buffers.insert(index, buffer);
+    }
+
+    /// The name of the file shown, if it is one.
+    pub(crate) fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub(crate) fn set_commands(&mut self, commands: Vec<CommandTemplate>) {
+        self.commands = commands;
+    }
+
+    pub(crate) fn set_output_format(&mut self, output_format: OutputFormat) {
+        self.output_format = output_format;
+    }
+
+    pub(crate) fn set_line_numbers(&mut self, line_numbers: LineNumbers) {
+        self.line_numbers = line_numbers;
+    }
+
+    pub(crate) fn set_wrap(&mut self, wrap: bool) {
+        self.wrap = wrap;
+    }
+
+    /// Pins `header` rows in every file, the ones added before too.
+    pub(crate) fn set_header(&mut self, header: usize) {
+        self.header = header;
+        self.header_override = Some(header);
+    }
+
+    /// Sets what to copy for each row, in place of its text.
+    pub(crate) fn set_copies(&mut self, copies: Vec<String>) {
+        self.copies = copies;
+    }
+
+    /// The text that was copied, once the editor has quit.
+    pub(crate) fn copied(&self) -> Option<&str> {
+        match &self.should_quit {
+            ShouldQuit::Ye(CopyStatus::Success(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Why copying failed, once the editor has quit.
+    pub(crate) fn copy_error(&self) -> Option<&str> {
+        match &self.should_quit {
+            ShouldQuit::Ye(CopyStatus::Error(e)) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// The command line to replace lcp with, once the editor has quit.
+    pub(crate) fn exec_command(&self) -> Option<&str> {
+        match &self.should_quit {
+            ShouldQuit::Exec(command_line) => Some(command_line),
+            _ => None,
+        }
+    }
+
     fn draw(&mut self) -> std::io::Result<()> {
         self.document.highlight(&self.highlighted_text);
         self.draw_rows()?;
@@ -142,7 +391,9 @@ impl Editor {
         // They need to be reset on each loop or the position will affect
         // where we start outputting on the tty.
         self.terminal.cursor_position(&Position::default())?;
-        if let ShouldQuit::Ye(copy_status) = &self.should_quit {
+        if let ShouldQuit::Exec(_) = &self.should_quit {
+            self.terminal.clear_screen()?;
+        } else if let ShouldQuit::Ye(copy_status) = &self.should_quit {
             self.terminal.clear_screen()?;
             match copy_status {
                 CopyStatus::Noop => self.terminal.writeln("Copied Nothing.")?,
@@ -190,10 +441,25 @@ impl Editor {
         self.status_message = SEARCH_STRING.to_string();
     }
 
-    fn visual_mode(&mut self) {
+    /// Starts highlighting `selection` from the visual cursor, or switches the
+    /// shape of the current highlighting. Otherwise (re)starts the visual cursor,
+    /// except for line selections which start right away.
+    fn visual_mode(&mut self, selection: Selection) {
         self.prompt_input = "".to_string();
-        if let InputMode::Visual(VisualMode::Cursor) = self.input_mode {
-            self.input_mode = InputMode::Visual(VisualMode::Block);
+        let visual_mode = match selection {
+            Selection::Block => VisualMode::Block,
+            Selection::Stream => VisualMode::Stream,
+            Selection::Line => VisualMode::Line,
+        };
+        let highlighting = matches!(
+            self.input_mode,
+            InputMode::Visual(VisualMode::Block | VisualMode::Stream | VisualMode::Line)
+        );
+        if self.input_mode == InputMode::Visual(VisualMode::Cursor)
+            || (highlighting && self.input_mode != InputMode::Visual(visual_mode))
+        {
+            self.input_mode = InputMode::Visual(visual_mode);
+            self.highlighted_text.set_selection(selection);
             self.status_message = VISUAL_BLOCK_STRING.to_string();
         } else {
             if let InputMode::Normal = self.input_mode {
@@ -202,14 +468,300 @@ impl Editor {
             self.input_mode = InputMode::Visual(VisualMode::Cursor);
             self.highlighted_text = HighlightedText::new_visual(self.cursor_position);
             self.status_message = VISUAL_CURSOR_STRING.to_string();
+            if selection == Selection::Line {
+                self.visual_mode(selection);
+            }
+        }
+    }
+
+    fn structure_mode(&mut self) {
+        let Position { x, y, .. } = self.cursor_position;
+        let Some(token) = structure::token(&self.document, x, y) else {
+            return;
+        };
+        self.input_mode = InputMode::Structure;
+        self.structure = vec![token];
+        self.expand_structure();
+    }
+
+    fn expand_structure(&mut self) {
+        if let Some(span) = self
+            .structure
+            .last()
+            .and_then(|span| structure::expand(&self.document, span))
+        {
+            self.structure.push(span);
+        }
+        self.select_structure();
+    }
+
+    fn shrink_structure(&mut self) {
+        self.structure.pop();
+        self.select_structure();
+        if self.structure.len() <= 1 {
+            self.normal_mode();
+        }
+    }
+
+    fn select_structure(&mut self) {
+        let Some(&span) = self.structure.last() else {
+            return;
+        };
+        self.select_bytes(span.start, span.end);
+        self.status_message = format!("{}: {}", span.kind.as_str(), STRUCTURE_STRING);
+    }
+
+    /// Selects a stream from the `(y, x)` of `start` to the one of `end`,
+    /// given as byte offsets.
+    fn select_bytes(&mut self, start: (usize, usize), end: (usize, usize)) {
+        let longest_row = self.document.longest_row();
+        let position = |(y, x): (usize, usize)| Position {
+            x: self.row(y).grapheme(x),
+            y,
+            longest_row,
+        };
+        let (start, end) = (position(start), position(end));
+        self.highlighted_text = HighlightedText::new_visual(start);
+        self.highlighted_text.set_selection(Selection::Stream);
+        self.highlighted_text.update_position(end);
+        self.cursor_position = start;
+        self.scroll();
+    }
+
+    fn process_keypress_structure(&mut self, pressed_key: Key) {
+        match pressed_key {
+            Key::Char('+') => self.expand_structure(),
+            Key::Char('-') => self.shrink_structure(),
+            Key::Char('\r' | '\n') => self.copy_and_exit(),
+            Key::Esc => self.normal_mode(),
+            _ => {}
+        }
+    }
+
+    fn tree_mode(&mut self) {
+        let Position { x, y, .. } = self.cursor_position;
+        let row = self.row(y);
+        let x = row.token(x).map_or(0, |token| row.byte(token.start));
+        let Some(tree) = self.document.tree() else {
+            self.status_message = match self.document.is_too_large_for_tree() {
+                true => LARGE_TREE_STRING,
+                false => NO_TREE_STRING,
+            }
+            .to_string();
+            return;
+        };
+        let node = tree.node_at(y, x);
+        self.input_mode = InputMode::Tree;
+        self.select_node(node);
+    }
+
+    fn select_node(&mut self, index: usize) {
+        let Some(node) = self.document.tree().map(|tree| tree.node(index)) else {
+            return;
+        };
+        let (start, end) = (node.start, node.end);
+        self.tree_node = index;
+        self.select_bytes(start, end);
+        self.status_message = TREE_STRING.to_string();
+    }
+
+    fn process_keypress_tree(&mut self, pressed_key: Key) {
+        let Some(tree) = self.document.tree() else {
+            return;
+        };
+        let node = tree.node(self.tree_node);
+        match pressed_key {
+            Key::Up if self.tree_node > 0 => self.select_node(self.tree_node - 1),
+            Key::Down if self.tree_node + 1 < tree.len() => self.select_node(self.tree_node + 1),
+            Key::Left => {
+                if let Some(parent) = node.parent {
+                    self.select_node(parent);
+                }
+            }
+            Key::Right => {
+                if let Some(&child) = node.children.first() {
+                    self.select_node(child);
+                }
+            }
+            Key::Char('\r' | '\n') => {
+                let value = tree.value(self.tree_node);
+                self.copy_text_and_exit(value);
+            }
+            Key::Char('p') => {
+                let path = tree.path(self.tree_node);
+                self.copy_text_and_exit(path);
+            }
+            Key::Char('s') => {
+                let subtree = tree.serialize(self.tree_node);
+                self.copy_text_and_exit(subtree);
+            }
+            Key::Esc => self.normal_mode(),
+            _ => {}
+        }
+    }
+
+    fn command_mode(&mut self) {
+        if self.commands.is_empty() {
+            self.status_message = NO_COMMANDS_STRING.to_string();
+            return;
+        }
+        let previous = match self.input_mode {
+            InputMode::Visual(visual_mode) => Some(visual_mode),
+            _ => None,
+        };
+        self.input_mode = InputMode::Command(previous);
+        let bindings: Vec<String> = self
+            .commands
+            .iter()
+            .map(|command| format!("{} = {}", command.key, command.template))
+            .collect();
+        self.status_message = format!("{}{}", COMMAND_STRING, bindings.join(" | "));
+    }
+
+    fn process_keypress_command(&mut self, previous: Option<VisualMode>, pressed_key: Key) {
+        self.input_mode = match previous {
+            None => InputMode::Normal,
+            Some(visual_mode) => InputMode::Visual(visual_mode),
+        };
+        self.status_message = self.input_mode.help().to_string();
+        let command = match pressed_key {
+            Key::Char(c) => self.commands.iter().find(|command| command.key == c),
+            _ => None,
+        };
+        let Some(command) = command.cloned() else {
+            return;
+        };
+        let selection = self.document.get_text(&self.highlighted_text);
+        if selection.is_empty() {
+            self.status_message = "Nothing is selected.".to_string();
+            return;
+        }
+        let command_line = command.command_line(&selection);
+        match command.action {
+            Action::Exec => self.should_quit = ShouldQuit::Exec(command_line),
+            Action::View => self.open_view(command_line),
+        }
+    }
+
+    /// Replaces the document with the output of `command_line`, keeping the
+    /// current one to go back to.
+    fn open_view(&mut self, command_line: String) {
+        let document = match command::output(&command_line)
+            .and_then(|output| Document::new(output.as_slice()))
+        {
+            Ok(document) => document,
+            Err(e) => {
+                self.status_message = format!("Failed to run {command_line}: {e}");
+                return;
+            }
+        };
+        if document.is_blank() {
+            self.status_message = format!("No output from {command_line}");
+            return;
+        }
+        let previous = self.swap_view(View::new(document));
+        self.views.push(previous);
+        self.status_message = format!("{command_line} (ESC to go back)");
+    }
+
+    fn close_view(&mut self, view: View) {
+        self.swap_view(view);
+    }
+
+    /// Shows the document of `view` as it was left, and returns the state of
+    /// the one shown before.
+    fn swap_view(&mut self, view: View) -> View {
+        let previous = View {
+            document: std::mem::replace(&mut self.document, view.document),
+            cursor_position: self.cursor_position,
+            offset: self.offset,
+            highlighted_text: std::mem::replace(&mut self.highlighted_text, view.highlighted_text),
+            input_mode: self.input_mode,
+            prompt_input: std::mem::replace(&mut self.prompt_input, view.prompt_input),
+            wrap_offset: self.wrap_offset,
+            header: self.header,
+        };
+        self.cursor_position = view.cursor_position;
+        self.offset = view.offset;
+        self.input_mode = view.input_mode;
+        self.wrap_offset = view.wrap_offset;
+        self.header = view.header;
+        self.status_message = match self.input_mode {
+            InputMode::Search(_) => format!("{}{}", SEARCH_STRING, self.prompt_input),
+            _ => self.input_mode.help().to_string(),
+        };
+        previous
+    }
+
+    /// Shows the next file, or the previous one when going `Backward`.
+    fn switch_buffer(&mut self, direction: SearchDirection) {
+        let files = self.buffers.len() + 1;
+        let buffer = match direction {
+            SearchDirection::Forward => self.buffers.pop_front(),
+            SearchDirection::Backward => self.buffers.pop_back(),
+        };
+        let Some(buffer) = buffer else {
+            self.status_message = "There are no other files.".to_string();
+            return;
+        };
+        let previous = Buffer {
+            name: std::mem::replace(&mut self.name, buffer.name),
+            views: std::mem::replace(&mut self.views, buffer.views),
+            view: self.swap_view(buffer.view),
+        };
+        // The output of a command shown in the file keeps its own header
+        if self.views.is_empty()
+            && let Some(header) = self.header_override
+        {
+            self.header = header;
+        }
+        match direction {
+            SearchDirection::Forward => {
+                self.buffers.push_back(previous);
+                self.buffer = (self.buffer + 1) % files;
+            }
+            SearchDirection::Backward => {
+                self.buffers.push_front(previous);
+                self.buffer = (self.buffer + files - 1) % files;
+            }
+        }
+    }
+
+    fn ex_mode(&mut self) {
+        self.input_mode = InputMode::Ex;
+        self.prompt_input = "".to_string();
+        self.status_message = EX_STRING.to_string();
+    }
+
+    fn process_keypress_ex(&mut self, pressed_key: Key) {
+        match pressed_key {
+            Key::Backspace => {
+                self.prompt_input.pop();
+            }
+            Key::Char('\n') => {
+                let command = std::mem::take(&mut self.prompt_input);
+                self.normal_mode();
+                match command.trim() {
+                    "bn" | "bnext" => self.switch_buffer(SearchDirection::Forward),
+                    "bp" | "bprevious" => self.switch_buffer(SearchDirection::Backward),
+                    other => self.status_message = format!("Not a command: {other}"),
+                }
+                return;
+            }
+            Key::Char(c) if !c.is_control() => self.prompt_input.push(c),
+            Key::Esc => {
+                self.normal_mode();
+                return;
+            }
+            _ => (),
         }
+        self.status_message = format!("{}{}", EX_STRING, self.prompt_input);
     }
 
     fn process_keypress_tokenizer(&mut self, pressed_key: Key) {
         match pressed_key {
             Key::Backspace => {
-                self.prompt_input
-                    .truncate(self.prompt_input.len().saturating_sub(1));
+                self.prompt_input.pop();
                 self.status_message = format!("{}{}", TOKENIZER_STRING, self.prompt_input)
             }
             Key::Char('\n') => {
@@ -235,8 +787,7 @@ impl Editor {
         let mut current_direction = search_direction;
         match pressed_key {
             Key::Backspace if !self.prompt_input.is_empty() => {
-                self.prompt_input
-                    .truncate(self.prompt_input.len().saturating_sub(1));
+                self.prompt_input.pop();
             }
             Key::Right | Key::Down => {
                 if let InputMode::Search(ref mut direction) = self.input_mode {
@@ -255,10 +806,17 @@ impl Editor {
             Key::Char('\n') => {
                 self.copy_and_exit();
             }
-            Key::Char(c) => {
-                if !c.is_control() {
-                    self.prompt_input.push(c);
-                }
+            // The search goes on when coming back to this file.
+            Key::Char('\t') => {
+                self.switch_buffer(SearchDirection::Forward);
+                return;
+            }
+            Key::BackTab => {
+                self.switch_buffer(SearchDirection::Backward);
+                return;
+            }
+            Key::Char(c) if !c.is_control() => {
+                self.prompt_input.push(c);
             }
             Key::Esc => {
                 self.prompt_input.truncate(0);
@@ -277,7 +835,7 @@ impl Editor {
                 Some(position) => {
                     self.cursor_position = position;
                     self.scroll();
-                    Some(self.prompt_input.len())
+                    Some(self.prompt_input.graphemes(true).count())
                 }
             };
 
@@ -299,23 +857,69 @@ impl Editor {
             InputMode::Visual(_) => {
                 self.process_keypress_normal(pressed_key)?;
             }
+            InputMode::Structure => {
+                self.process_keypress_structure(pressed_key);
+            }
+            InputMode::Tree => {
+                self.process_keypress_tree(pressed_key);
+            }
+            InputMode::Command(previous) => {
+                self.process_keypress_command(*previous, pressed_key);
+            }
+            InputMode::Ex => {
+                self.process_keypress_ex(pressed_key);
+            }
         }
         Ok(())
     }
 
     fn copy_and_exit(&mut self) {
-        let s = self.document.get_text(&self.highlighted_text);
+        let mut text = self.highlighted_text.clone();
+        // Columns are copied without the header of the table
+        if let TextMode::Visual(mut start, Selection::Block) = text.mode
+            && start.y.max(text.position.y) >= self.header
+        {
+            start.y = start.y.max(self.header);
+            text.position.y = text.position.y.max(self.header);
+            text.mode = TextMode::Visual(start, Selection::Block);
+        }
+        let s = self
+            .copy_of_rows(&text)
+            .unwrap_or_else(|| self.document.get_text(&text));
+        self.copy_text_and_exit(s);
+    }
+
+    /// What to copy for the rows picked by `text`, when they stand for
+    /// something else.
+    fn copy_of_rows(&self, text: &HighlightedText) -> Option<String> {
+        // Only whole rows stand for their copies
+        if !self.views.is_empty() || *self.document.tokenizer() != Tokenizer::Line {
+            return None;
+        }
+        let (start, end) = match text.mode {
+            TextMode::Token => (text.position.y, text.position.y),
+            TextMode::Visual(start, Selection::Line) => switch_start_end(start.y, text.position.y),
+            _ => return None,
+        };
+        let copies: Option<Vec<&str>> = (start..=end)
+            .map(|y| self.copies.get(y).map(String::as_str))
+            .collect();
+        Some(copies?.join("\n"))
+    }
+
+    fn copy_text_and_exit(&mut self, s: String) {
         if s.is_empty() {
             self.should_quit = ShouldQuit::Ye(CopyStatus::Noop);
             return;
         }
-        let copy_status = match self.clipboard.as_mut() {
-            None => CopyStatus::Success(s.to_string()),
-            Some(clipboard) => match clipboard.set_text(&s) {
-                Ok(_) => CopyStatus::Success(s.to_string()),
-                Err(e) => CopyStatus::Error(e.to_string()),
-            },
-        };
+        let s = self.output_format.apply(&s);
+        let mut copy_status = CopyStatus::Success(s.to_string());
+        for clipboard in self.clipboards.iter_mut() {
+            if let Err(e) = clipboard.set_text(&s) {
+                copy_status = CopyStatus::Error(e);
+                break;
+            }
+        }
         self.should_quit = ShouldQuit::Ye(copy_status);
     }
 
@@ -324,21 +928,61 @@ impl Editor {
             Key::Esc => {
                 if let InputMode::Visual(_) = self.input_mode {
                     self.normal_mode()
+                } else if let Some(view) = self.views.pop() {
+                    self.close_view(view);
+                    return Ok(());
                 } else {
                     self.should_quit = ShouldQuit::Ye(CopyStatus::Noop)
                 }
             }
+            Key::Char('!') => {
+                self.command_mode();
+                return Ok(());
+            }
             Key::Char('/') => {
                 self.search_mode();
                 return Ok(());
             }
+            Key::Char(':') if self.input_mode == InputMode::Normal => {
+                self.ex_mode();
+                return Ok(());
+            }
+            Key::Char('\t') => {
+                self.switch_buffer(SearchDirection::Forward);
+                return Ok(());
+            }
+            Key::BackTab => {
+                self.switch_buffer(SearchDirection::Backward);
+                return Ok(());
+            }
             Key::Char('\r') | Key::Char('\n') => self.copy_and_exit(),
             Key::Char('t') => self.token_mode(),
             Key::Char('v') => {
-                self.visual_mode();
+                self.visual_mode(Selection::Block);
+                return Ok(());
+            }
+            Key::Char('+') if self.input_mode == InputMode::Normal => {
+                self.structure_mode();
+                return Ok(());
+            }
+            Key::Char('p') if self.input_mode == InputMode::Normal => {
+                self.tree_mode();
+                return Ok(());
+            }
+            Key::Char('V') => {
+                self.visual_mode(Selection::Line);
+                return Ok(());
+            }
+            Key::Char('s') if matches!(self.input_mode, InputMode::Visual(_)) => {
+                self.visual_mode(Selection::Stream);
                 return Ok(());
             }
             Key::Char('w') => self.document.update_tokenizer(Tokenizer::Whitespace),
+            Key::Char('#') => self.line_numbers = self.line_numbers.next(),
+            Key::Char('W') => {
+                self.wrap = !self.wrap;
+                self.wrap_offset = 0;
+            }
             Key::Up
             | Key::Down
             | Key::Left
@@ -358,33 +1002,176 @@ impl Editor {
             InputMode::Visual(VisualMode::Cursor) => {
                 self.highlighted_text = HighlightedText::new_visual(self.cursor_position)
             }
-            InputMode::Visual(VisualMode::Block) => {
+            InputMode::Visual(VisualMode::Block | VisualMode::Stream | VisualMode::Line) => {
                 self.highlighted_text.update_position(self.cursor_position);
             }
             _ => {}
         };
         Ok(())
     }
-    fn scroll(&mut self) {
+    /// The width of the line numbers, including the space after them.
+    fn gutter_width(&self) -> usize {
+        match self.line_numbers {
+            LineNumbers::Off => 0,
+            // Wider as more rows are found, when the document is huge
+            _ => self.document.rows_found().0.to_string().len() + 1,
+        }
+    }
+
+    /// The number of columns left for the rows.
+    fn text_width(&self) -> usize {
+        (self.terminal.size().width as usize).saturating_sub(self.gutter_width())
+    }
+
+    /// The grapheme the cursor is on, which is the start of the token in
+    /// normal mode.
+    fn cursor_column(&self) -> usize {
         let Position { x, y, .. } = self.cursor_position;
-        let width = self.terminal.size().width as usize;
-        let height = self.terminal.size().height as usize;
-        if y < self.offset.y {
-            self.offset.y = y;
-        } else if y >= self.offset.y.saturating_add(height) {
-            self.offset.y = y.saturating_sub(height).saturating_add(1);
+        match self.input_mode {
+            InputMode::Normal => self.row(y).token(x).map_or(0, |token| token.start),
+            _ => x,
+        }
+    }
+
+    /// Row `y`, or the last row when `y` is past the end, like the cursor
+    /// when it is moved past it.
+    fn row(&self, y: usize) -> Rc<Row> {
+        let Some(row) = self.document.row(self.document.clamp(y)) else {
+            unreachable!("a document has rows up to its last");
+        };
+        row
+    }
+
+    /// The screen lines of a row in wrap mode.
+    fn screen_lines(&self, index: usize) -> Vec<(usize, usize)> {
+        self.row(index).wrap(self.text_width())
+    }
+
+    /// The screen line of its row the cursor is on in wrap mode.
+    fn cursor_screen_line(&self) -> usize {
+        let column = self.cursor_column();
+        self.screen_lines(self.cursor_position.y)
+            .iter()
+            .rposition(|&(start, _)| start <= column)
+            .unwrap_or_default()
+    }
+
+    fn scroll(&mut self) {
+        if self.wrap {
+            self.scroll_wrapped();
+            return;
+        }
+        let y = self.cursor_position.y;
+        let x = self.cursor_column();
+        let width = self.text_width();
+        let header = self.pinned_rows();
+        let height = self.body_height();
+        // The rows below the header start at `offset.y + header`
+        if y < header {
+            // The header is always on the screen
+        } else if y < self.offset.y.saturating_add(header) {
+            self.offset.y = y - header;
+        } else if y >= self.offset.y.saturating_add(header + height) {
+            self.offset.y = y + 1 - header - height;
         }
         if x < self.offset.x {
             self.offset.x = x;
-        } else if x >= self.offset.x.saturating_add(width) {
-            self.offset.x = x.saturating_sub(width).saturating_add(1);
+        }
+        // Wide graphemes take more than a column of the screen
+        let row = self.row(y);
+        while self.offset.x < x && row.width(self.offset.x, x + 1) > width {
+            self.offset.x += 1;
+        }
+    }
+    /// Keeps the cursor's screen line on the screen, counting screen lines
+    /// instead of rows.
+    fn scroll_wrapped(&mut self) {
+        let y = self.cursor_position.y;
+        let line = self.cursor_screen_line();
+        let header = self.pinned_rows();
+        let height = self.body_height();
+        self.offset.x = 0;
+        if y < header {
+            return;
+        }
+        let mut top = self.offset.y + header;
+        if (y, line) < (top, self.wrap_offset) {
+            self.offset.y = y - header;
+            self.wrap_offset = line;
+            return;
+        }
+        // Every row takes at least a screen line
+        if y - top >= height {
+            top = y + 1 - height;
+            self.wrap_offset = 0;
+        }
+        let mut lines: usize = (top..y)
+            .map(|index| self.screen_lines(index).len())
+            .sum::<usize>()
+            + line
+            + 1
+            - self.wrap_offset;
+        while lines > height {
+            self.wrap_offset += 1;
+            if self.wrap_offset == self.screen_lines(top).len() {
+                top += 1;
+                self.wrap_offset = 0;
+            }
+            lines -= 1;
+        }
+        self.offset.y = top - header;
+    }
+
+    /// The header rows that are pinned, leaving at least a row to scroll.
+    fn pinned_rows(&self) -> usize {
+        let height = self.terminal.size().height as usize;
+        let header = self.header.min(height.saturating_sub(1));
+        (0..header)
+            .take_while(|&index| self.document.has_row(index))
+            .count()
+    }
+
+    /// The number of screen lines below the header.
+    fn body_height(&self) -> usize {
+        let header = self.pinned_rows();
+        let header_lines = if self.wrap {
+            (0..header)
+                .map(|index| self.screen_lines(index).len())
+                .sum()
+        } else {
+            header
+        };
+        (self.terminal.size().height as usize)
+            .saturating_sub(header_lines)
+            .max(1)
+    }
+
+    /// How many rows fit on a screen from row `y` on, going up or down.
+    fn page_rows(&self, y: usize, down: bool) -> usize {
+        let height = self.body_height();
+        if !self.wrap {
+            return height;
+        }
+        let mut lines = 0;
+        let mut rows = 0;
+        let mut index = y;
+        loop {
+            index = match down {
+                true if self.document.has_row(index + 1) => index + 1,
+                false if index > 0 => index - 1,
+                _ => return rows.max(1),
+            };
+            lines += self.screen_lines(index).len();
+            if lines > height {
+                return rows.max(1);
+            }
+            rows += 1;
         }
     }
+
     fn move_cursor(&mut self, key: Key) {
-        let terminal_height = self.terminal.size().height as usize;
         let Position { mut y, mut x, .. } = self.cursor_position;
-        let height = self.document.len() - 1;
-        let row = self.document.row(y);
+        let row = self.row(y);
         let width = row.len(self.highlighted_text.mode);
         match key {
             Key::Char('$') => {
@@ -393,20 +1180,8 @@ impl Editor {
             Key::Char('^') => {
                 x = 0;
             }
-            Key::Up => {
-                if y == 0 {
-                    y = height;
-                } else {
-                    y = y.saturating_sub(1)
-                }
-            }
-            Key::Down => {
-                if y < height {
-                    y = y.saturating_add(1);
-                } else {
-                    y = 0;
-                }
-            }
+            Key::Up => y = self.previous_row(y),
+            Key::Down => y = self.next_row(y),
             Key::Left => {
                 if x > 0 {
                     x -= 1;
@@ -422,25 +1197,37 @@ impl Editor {
                 }
             }
             Key::PageUp => {
-                y = if y > terminal_height {
-                    y.saturating_sub(terminal_height)
-                } else {
-                    0
-                }
+                let rows = self.page_rows(y, false);
+                y = if y > rows { y.saturating_sub(rows) } else { 0 }
             }
             Key::PageDown => {
-                y = if y.saturating_add(terminal_height) < height {
-                    y.saturating_add(terminal_height)
-                } else {
-                    height
-                }
+                let rows = self.page_rows(y, true);
+                y = self.document.clamp(y.saturating_add(rows));
             }
             Key::Home => x = 0,
             Key::End => x = width,
             _ => (),
         }
-        let row = self.document.row(y);
-        let width = row.len(self.highlighted_text.mode);
+        // Rows without tokens are skipped, going on in the same direction.
+        // If no row has any, the cursor stays where it landed.
+        let mode = self.highlighted_text.mode;
+        if self.row(y).len(mode) == usize::MAX {
+            let mut next = y;
+            loop {
+                next = match key {
+                    Key::Up | Key::PageUp => self.previous_row(next),
+                    _ => self.next_row(next),
+                };
+                if next == y {
+                    break;
+                }
+                if self.row(next).len(mode) != usize::MAX {
+                    y = next;
+                    break;
+                }
+            }
+        }
+        let width = self.row(y).len(mode);
 
         if x > width {
             x = width;
@@ -451,78 +1238,187 @@ impl Editor {
             y,
             longest_row: self.document.longest_row(),
         };
+    }
 
-        // If there are no tokens, move again.
-        // We assume this happens only when moving the cursor up or down.
-        if width == usize::MAX {
-            match key {
-                Key::Up => self.move_cursor(Key::Up),
-                Key::Down => self.move_cursor(Key::Down),
-                _ => self.move_cursor(Key::Down),
-            }
+    /// The row above `y`, going round to the last one.
+    fn previous_row(&self, y: usize) -> usize {
+        match y {
+            0 => self.document.len() - 1,
+            _ => y - 1,
         }
     }
 
-    pub fn draw_row(&mut self, index: usize) -> std::io::Result<()> {
-        let row = self.document.row(index);
-        let width = self.terminal.size().width as usize;
-        let start = self.offset.x;
-        let end = self.offset.x.saturating_add(width);
-        let row = row.render(start, end);
-        self.terminal.writeln(&row)
+    /// The row below `y`, going round to the first one.
+    fn next_row(&self, y: usize) -> usize {
+        match self.document.has_row(y + 1) {
+            true => y + 1,
+            false => 0,
+        }
+    }
+
+    /// The characters `start..end` of a row, as drawn. Only the first screen
+    /// line of a row is numbered.
+    fn screen_line(&self, index: usize, (start, end): (usize, usize), first: bool) -> String {
+        let gutter = if first {
+            self.gutter(index)
+        } else {
+            " ".repeat(self.gutter_width())
+        };
+        let highlighted = self.document.highlighted(index);
+        let row = self
+            .row(index)
+            .render(start, end, self.text_width(), highlighted);
+        format!("{gutter}{row}")
+    }
+
+    fn gutter(&self, index: usize) -> String {
+        let y = self.cursor_position.y;
+        let number = match self.line_numbers {
+            LineNumbers::Off => return String::new(),
+            LineNumbers::Relative if index != y => index.abs_diff(y),
+            _ => index.saturating_add(1),
+        };
+        format!(
+            "{}{:>width$} {}",
+            Fg(GUTTER_FG_COLOR),
+            number,
+            Fg(Color::Reset),
+            width = self.gutter_width() - 1
+        )
     }
 
     fn draw_rows(&mut self) -> std::io::Result<()> {
-        let height = self.terminal.size().height;
-        let doc_len = self.document.len() as u16;
-        for terminal_row in 0..height {
-            self.terminal.clear_current_line()?;
-            if terminal_row < doc_len {
-                self.draw_row(self.offset.y.saturating_add(terminal_row as usize))?;
+        if self.wrap {
+            return self.draw_wrapped_rows();
+        }
+        let header = self.pinned_rows();
+        for terminal_row in 0..self.terminal.size().height as usize {
+            let index = if terminal_row < header {
+                terminal_row
             } else {
-                self.terminal.writeln("~")?;
+                self.offset.y.saturating_add(terminal_row)
+            };
+            let line = match self.document.has_row(index) {
+                true => self.screen_line(index, (self.offset.x, usize::MAX), true),
+                false => "~".to_string(),
+            };
+            self.terminal.draw_line(terminal_row, &line)?;
+        }
+        Ok(())
+    }
+    fn draw_wrapped_rows(&mut self) -> std::io::Result<()> {
+        let height = self.terminal.size().height as usize;
+        let header = self.pinned_rows();
+        // The row and characters of every screen line, the header's first
+        let mut screen = Vec::new();
+        let rows = (0..header)
+            .chain((self.offset.y + header..).take_while(|&index| self.document.has_row(index)));
+        for (i, index) in rows.enumerate() {
+            if screen.len() >= height {
+                break;
+            }
+            let skipped = if i == header { self.wrap_offset } else { 0 };
+            let lines = self.screen_lines(index);
+            for (line, range) in lines.into_iter().enumerate().skip(skipped) {
+                screen.push((index, range, line == 0));
             }
         }
+        for terminal_row in 0..height {
+            let line = match screen.get(terminal_row) {
+                Some(&(index, range, first)) => self.screen_line(index, range, first),
+                None => "~".to_string(),
+            };
+            self.terminal.draw_line(terminal_row, &line)?;
+        }
         Ok(())
     }
+
     fn draw_status_bar(&mut self) -> std::io::Result<()> {
         let width = self.terminal.size().width as usize;
 
-        let mut line_indicator = format!(
-            "{}/{} lines. Mode: {}. Tokenizer: {}",
+        let mut line_indicator = String::new();
+        if let Some(name) = &self.name {
+            line_indicator.push_str(name);
+            if !self.buffers.is_empty() {
+                line_indicator.push_str(&format!(
+                    " ({}/{})",
+                    self.buffer + 1,
+                    self.buffers.len() + 1
+                ));
+            }
+            line_indicator.push_str(". ");
+        }
+        // Huge documents are still being looked through
+        let (len, complete) = self.document.rows_found();
+        line_indicator.push_str(&format!(
+            "{}/{}{} lines. Mode: {}. Tokenizer: {}",
             self.cursor_position.y.saturating_add(1),
-            self.document.len(),
+            len,
+            if complete { "" } else { "+" },
             self.input_mode.as_str(),
             self.document.tokenizer().as_str()
-        );
+        ));
+        if self.document.encoding() != Encoding::Utf8 {
+            line_indicator.push_str(&format!(
+                ". Encoding: {}",
+                self.document.encoding().as_str()
+            ));
+        }
+        if self.input_mode == InputMode::Tree
+            && let Some(tree) = self.document.tree()
+        {
+            line_indicator.push_str(&format!(". Path: {}", tree.path(self.tree_node)));
+        }
 
-        let len = line_indicator.len();
-        line_indicator.push_str(&" ".repeat(width.saturating_sub(len)));
-        line_indicator.truncate(width);
-        self.terminal.set_bg_color(STATUS_BG_COLOR)?;
-        self.terminal.set_fg_color(STATUS_FG_COLOR)?;
-        self.terminal.writeln(&line_indicator.to_string())?;
-        self.terminal.reset_fg_color()?;
-        self.terminal.reset_bg_color()
+        let line_indicator = row::truncate(&line_indicator, width);
+        let padding = " ".repeat(width - line_indicator.width());
+        let status_bar = format!(
+            "{}{}{line_indicator}{padding}{}{}",
+            Bg(STATUS_BG_COLOR),
+            Fg(STATUS_FG_COLOR),
+            Fg(Color::Reset),
+            Bg(Color::Reset)
+        );
+        let y = self.terminal.size().height as usize;
+        self.terminal.draw_line(y, &status_bar)
     }
 
     fn draw_message_bar(&mut self) -> std::io::Result<()> {
-        self.terminal.clear_current_line()?;
-        let mut message = self.status_message.clone();
-        message.truncate(self.terminal.size().width as usize);
-        self.terminal.write(&message)?;
-        Ok(())
+        let width = self.terminal.size().width as usize;
+        let message = row::truncate(&self.status_message, width).to_string();
+        let y = self.terminal.size().height as usize + 1;
+        self.terminal.draw_line(y, &message)
+    }
+
+    /// Moves the cursor to the last token of the document, e.g. the most
+    /// recent output of a captured terminal pane.
+    pub(crate) fn jump_to_bottom(&mut self) {
+        self.cursor_position.y = 0;
+        self.move_cursor(Key::Up);
+        self.move_cursor(Key::End);
+        self.scroll();
+        self.highlighted_text = HighlightedText::new_token(self.cursor_position);
     }
 
-    fn die(&mut self, e: std::io::Error) -> std::io::Result<()> {
-        self.terminal.clear_screen()?;
-        self.terminal.cursor_show()?;
-        panic!("{e}");
+    /// Moves the cursor to row `line`, counting from 1, or the last row.
+    pub(crate) fn jump_to_line(&mut self, line: usize) {
+        self.cursor_position.y = self.document.clamp(line.saturating_sub(1));
+        self.cursor_position.x = 0;
+        self.scroll();
+        self.highlighted_text = HighlightedText::new_token(self.cursor_position);
+    }
+
+    /// Starts searching as if `query` had been typed after `/`.
+    pub(crate) fn start_search(&mut self, query: &str) {
+        self.search_mode();
+        for c in query.chars() {
+            self.process_keypress_search(SearchDirection::Forward, Key::Char(c));
+        }
     }
 
     fn token_cursor(&mut self) {
         let Position { x, y, longest_row } = self.cursor_position;
-        let row = self.document.row(y);
+        let row = self.row(y);
         for (i, tok) in row.tokens.iter().enumerate() {
             if tok.start <= x {
                 self.cursor_position = Position {
@@ -536,7 +1432,7 @@ impl Editor {
 
     fn normal_cursor(&mut self) {
         let Position { x, y, longest_row } = self.cursor_position;
-        let row = self.document.row(y);
+        let row = self.row(y);
         if let Some(tok) = row.token(x) {
             self.cursor_position = Position {
                 x: tok.start,
@@ -0,0 +1,128 @@
+use std::borrow::Cow;
+use std::char::REPLACEMENT_CHARACTER;
+
+/// How much of the text is looked at to guess its encoding.
+const SAMPLE: usize = 64 * 1024;
+
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// The characters Windows-1252 puts in place of the C1 controls of Latin-1.
+/// The bytes it leaves undefined are undecodable.
+const WINDOWS_1252: [char; 32] = [
+    '€', '\u{fffd}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{fffd}', 'Ž',
+    '\u{fffd}', '\u{fffd}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{fffd}',
+    'ž', 'Ÿ',
+];
+
+/// The encoding of the input. Bytes that can't be decoded show as `�`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum Encoding {
+    #[default]
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// ISO-8859-1, every byte the character of the same code point.
+    Latin1,
+    /// Latin-1 with quotes and dashes in place of most control characters,
+    /// which is what text that isn't UTF-8 usually turns out to be.
+    Windows1252,
+}
+
+impl Encoding {
+    pub(crate) fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(Encoding::Utf8),
+            "utf-16le" | "utf16le" => Some(Encoding::Utf16Le),
+            "utf-16be" | "utf16be" => Some(Encoding::Utf16Be),
+            "latin1" | "latin-1" | "iso-8859-1" => Some(Encoding::Latin1),
+            "windows-1252" | "cp1252" => Some(Encoding::Windows1252),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Latin1 => "Latin-1",
+            Encoding::Windows1252 => "Windows-1252",
+        }
+    }
+
+    /// Guesses the encoding of `text` from its byte order mark, or else from
+    /// whether its start is UTF-8. Text that has bytes UTF-8 can't decode and
+    /// no characters it can, beyond ASCII, is taken to be Windows-1252.
+    pub(crate) fn detect(text: &[u8]) -> Self {
+        match text {
+            [0xff, 0xfe, ..] => return Encoding::Utf16Le,
+            [0xfe, 0xff, ..] => return Encoding::Utf16Be,
+            _ => {}
+        }
+        let mut sample = &text[..text.len().min(SAMPLE)];
+        // Don't count a character cut off by the end of the sample as invalid.
+        if sample.len() < text.len()
+            && let Some(end) = memchr::memrchr(b'\n', sample)
+        {
+            sample = &sample[..end];
+        }
+        let (mut decoded, mut undecodable) = (false, false);
+        for chunk in sample.utf8_chunks() {
+            decoded |= !chunk.valid().is_ascii();
+            undecodable |= !chunk.invalid().is_empty();
+        }
+        if undecodable && !decoded {
+            Encoding::Windows1252
+        } else {
+            Encoding::Utf8
+        }
+    }
+
+    /// Whether lines have to be decoded before their line breaks can be found.
+    pub(crate) fn is_wide(&self) -> bool {
+        matches!(self, Encoding::Utf16Le | Encoding::Utf16Be)
+    }
+
+    /// `text`, in a wide encoding, as UTF-8, without its byte order mark.
+    pub(crate) fn transcode(&self, text: &[u8]) -> Vec<u8> {
+        let (units, odd) = text.as_chunks::<2>();
+        let units = units.iter().map(|&unit| match self {
+            Encoding::Utf16Be => u16::from_be_bytes(unit),
+            _ => u16::from_le_bytes(unit),
+        });
+        let mut units = units.peekable();
+        units.next_if_eq(&0xfeff);
+        let mut decoded: String = char::decode_utf16(units)
+            .map(|c| c.unwrap_or(REPLACEMENT_CHARACTER))
+            .collect();
+        if !odd.is_empty() {
+            decoded.push(REPLACEMENT_CHARACTER);
+        }
+        decoded.into_bytes()
+    }
+
+    /// A line of text in this encoding, as UTF-8. Wide encodings are expected
+    /// to have been transcoded already.
+    pub(crate) fn decode<'a>(&self, line: &'a [u8]) -> Cow<'a, str> {
+        match self {
+            Encoding::Latin1 | Encoding::Windows1252 if line.is_ascii() => {
+                Cow::Borrowed(std::str::from_utf8(line).unwrap_or_default())
+            }
+            Encoding::Latin1 => Cow::Owned(line.iter().map(|&byte| char::from(byte)).collect()),
+            Encoding::Windows1252 => Cow::Owned(
+                line.iter()
+                    .map(|&byte| match byte {
+                        0x80..=0x9f => WINDOWS_1252[usize::from(byte - 0x80)],
+                        _ => char::from(byte),
+                    })
+                    .collect(),
+            ),
+            // Files joined with `cat` can have a byte order mark on any line
+            _ => String::from_utf8_lossy(line.strip_prefix(UTF8_BOM).unwrap_or(line)),
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/test_encoding.rs"]
+mod tests;
@@ -0,0 +1,59 @@
+use std::fmt;
+use std::io;
+
+/// Why lcp stopped without copying, as a one line message and an exit code.
+#[derive(Debug)]
+pub enum Error {
+    /// The command line arguments don't make sense.
+    Usage(String),
+    /// There is nothing to pick from.
+    Empty(String),
+    /// The config file can't be read or parsed.
+    Config(String),
+    /// Reading the input failed, with what was being read.
+    Io(String, io::Error),
+    /// Writing the copied text to `--output` failed.
+    Write(String, io::Error),
+    /// Setting up, drawing on or reading keys from the terminal failed.
+    Terminal(io::Error),
+    Clipboard(String),
+    /// The command lcp was to be replaced with couldn't run.
+    Command(String, io::Error),
+}
+
+impl Error {
+    pub(crate) fn exit_code(&self) -> i32 {
+        match self {
+            Error::Io(..) => 1,
+            Error::Usage(_) => 2,
+            Error::Empty(_) => 3,
+            Error::Config(_) => 4,
+            Error::Terminal(_) => 5,
+            Error::Clipboard(_) => 6,
+            Error::Write(..) => 7,
+            // What shells use for a command that can't be executed
+            Error::Command(..) => 126,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Usage(message) | Error::Empty(message) | Error::Config(message) => {
+                write!(f, "{message}")
+            }
+            Error::Io(what, e) => write!(f, "Failed to read {what}: {e}"),
+            Error::Write(path, e) => write!(f, "Failed to write {path}: {e}"),
+            Error::Terminal(e) => write!(f, "Terminal error: {e}"),
+            Error::Clipboard(message) => write!(f, "Clipboard error: {message}"),
+            Error::Command(command_line, e) => write!(f, "Failed to run {command_line}: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+#[path = "tests/test_error.rs"]
+mod tests;
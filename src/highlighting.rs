@@ -4,11 +4,23 @@ use crate::Position;
 pub(crate) enum TextMode {
     Token,
     /// Visual contains a position which is the starting position of the highlighting
-    Visual(Position),
+    Visual(Position, Selection),
     /// the str len is optional as there may be no matches to highlight
     Search(Option<usize>),
 }
 
+/// The shape of a visual selection between two positions.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum Selection {
+    /// The same columns on every row.
+    Block,
+    /// From the start position to the end of its row, whole rows in between,
+    /// then from the start of the last row to the end position.
+    Stream,
+    /// Whole rows.
+    Line,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub(crate) struct HighlightedText {
     pub(crate) position: Position,
@@ -32,7 +44,14 @@ impl HighlightedText {
     pub(crate) fn new_visual(position: Position) -> Self {
         Self {
             position,
-            mode: TextMode::Visual(position),
+            mode: TextMode::Visual(position, Selection::Block),
+        }
+    }
+
+    /// Changes the shape of a visual selection, keeping where it started.
+    pub(crate) fn set_selection(&mut self, selection: Selection) {
+        if let TextMode::Visual(start_position, _) = self.mode {
+            self.mode = TextMode::Visual(start_position, selection);
         }
     }
 
@@ -40,9 +59,3 @@ impl HighlightedText {
         self.position = position;
     }
 }
-
-#[derive(PartialEq, Eq, Clone, Copy, Debug)]
-pub enum Type {
-    None,
-    Highlighted,
-}
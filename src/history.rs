@@ -0,0 +1,169 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use unicode_width::UnicodeWidthStr;
+
+/// How many copies are remembered.
+const HISTORY_SIZE: usize = 100;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Entry {
+    /// Seconds since the unix epoch.
+    pub(crate) timestamp: u64,
+    pub(crate) source: String,
+    pub(crate) text: String,
+}
+
+/// Copies made by previous invocations, most recent first.
+///
+/// Stored as one `timestamp\tsource\ttext` line per entry, with tabs and
+/// newlines escaped.
+#[derive(Debug)]
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    pub(crate) fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// `$XDG_DATA_HOME/lcp/history`, falling back to `~/.local/share`.
+    pub(crate) fn default_path() -> Option<PathBuf> {
+        let data_home = match std::env::var_os("XDG_DATA_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".local/share"),
+        };
+        Some(data_home.join("lcp").join("history"))
+    }
+
+    pub(crate) fn entries(&self) -> io::Result<Vec<Entry>> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        Ok(contents.lines().filter_map(parse_entry).collect())
+    }
+
+    /// Records `text` as the most recent copy. Copying the same text again
+    /// moves it to the front instead of storing it twice.
+    pub(crate) fn push(&self, source: &str, text: &str) -> io::Result<()> {
+        let timestamp = now();
+        let mut entries = self.entries()?;
+        entries.retain(|entry| entry.text != text);
+        entries.insert(
+            0,
+            Entry {
+                timestamp,
+                source: source.to_string(),
+                text: text.to_string(),
+            },
+        );
+        entries.truncate(HISTORY_SIZE);
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let contents: String = entries
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{}\t{}\t{}\n",
+                    entry.timestamp,
+                    escape(&entry.source),
+                    escape(&entry.text)
+                )
+            })
+            .collect();
+        // Write to the side and rename so a concurrent lcp never reads half a file.
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, contents)?;
+        fs::rename(tmp, &self.path)
+    }
+}
+
+/// `entries` as text to open as a `Document`, a row for each: how long ago
+/// it was copied, where from and its text, with newlines escaped.
+pub(crate) fn render(entries: &[Entry], now: u64) -> String {
+    let sources: Vec<String> = entries.iter().map(|entry| escape(&entry.source)).collect();
+    let source_width = sources.iter().map(|source| source.width()).max();
+    entries
+        .iter()
+        .zip(&sources)
+        .map(|(entry, source)| {
+            let age = age(now.saturating_sub(entry.timestamp));
+            let padding = " ".repeat(source_width.unwrap_or_default() - source.width());
+            format!("{age:>7}  {source}{padding}  {}\n", escape(&entry.text))
+        })
+        .collect()
+}
+
+/// `seconds` as a short duration, in its largest unit.
+fn age(seconds: u64) -> String {
+    match seconds {
+        0..60 => "now".to_string(),
+        60..3600 => format!("{}m ago", seconds / 60),
+        3600..86400 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
+    }
+}
+
+/// Seconds since the unix epoch.
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn parse_entry(line: &str) -> Option<Entry> {
+    let mut fields = line.splitn(3, '\t');
+    let timestamp = fields.next()?.parse().ok()?;
+    let source = unescape(fields.next()?);
+    let text = unescape(fields.next()?);
+    Some(Entry {
+        timestamp,
+        source,
+        text,
+    })
+}
+
+fn escape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            '\t' => result.push_str("\\t"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+fn unescape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => result.push('\t'),
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some(c) => result.push(c),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+#[path = "tests/test_history.rs"]
+mod tests;
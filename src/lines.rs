@@ -0,0 +1,192 @@
+#[cfg(test)]
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, Read};
+use std::ops::Deref;
+
+use memmap2::Mmap;
+
+/// How many lines apart the offsets kept by the index are.
+const STRIDE: usize = 1024;
+
+/// The bytes of a document, mapped from a file or read from a stream.
+#[derive(Debug)]
+enum Text {
+    Mapped(Mmap),
+    Read(Vec<u8>),
+}
+
+impl Deref for Text {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Text::Mapped(map) => map,
+            Text::Read(bytes) => bytes,
+        }
+    }
+}
+
+/// Where the lines start, as far as they have been looked for.
+#[derive(Debug, Default)]
+struct Index {
+    /// The offset of every `STRIDE`th line.
+    offsets: Vec<usize>,
+    /// The number of lines found.
+    len: usize,
+    /// The offset of the line after the ones found.
+    end: usize,
+    /// Whether all lines have been found.
+    complete: bool,
+}
+
+/// The lines of a text, found when they are first asked for.
+///
+/// Only some line offsets are kept, so the index of a file with millions of
+/// lines stays small, and opening it doesn't wait for all of them to be found.
+#[derive(Debug)]
+pub(crate) struct Lines {
+    text: Text,
+    index: RefCell<Index>,
+    /// How many bytes were looked through for line breaks.
+    #[cfg(test)]
+    pub(crate) scanned: Cell<usize>,
+}
+
+impl Lines {
+    pub(crate) fn read(mut input: impl Read) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        input.read_to_end(&mut bytes)?;
+        Ok(Self::from_bytes(bytes))
+    }
+
+    pub(crate) fn from_bytes(bytes: Vec<u8>) -> Self {
+        Self::new(Text::Read(bytes))
+    }
+
+    /// Maps the file into memory instead of reading it.
+    pub(crate) fn map(file: &File) -> io::Result<Self> {
+        // SAFETY: The map is only read. Should another process truncate the
+        // file meanwhile, reading past its new end is a bus error, which is
+        // what `less` risks as well.
+        let map = unsafe { Mmap::map(file)? };
+        Ok(Self::new(Text::Mapped(map)))
+    }
+
+    fn new(text: Text) -> Self {
+        Self {
+            text,
+            index: RefCell::new(Index::default()),
+            #[cfg(test)]
+            scanned: Cell::new(0),
+        }
+    }
+
+    /// The whole text.
+    pub(crate) fn bytes(&self) -> &[u8] {
+        &self.text
+    }
+
+    /// Line `index` without its line break.
+    pub(crate) fn get(&self, index: usize) -> Option<&[u8]> {
+        let start = self.start(index)?;
+        Some(&self.text[start..self.line_end(start)])
+    }
+
+    /// Lines `index` and on, with their numbers, found in one pass over the
+    /// text instead of from the index for each.
+    pub(crate) fn forward(&self, index: usize) -> impl Iterator<Item = (usize, &[u8])> {
+        let mut start = self.start(index);
+        (index..).map_while(move |i| {
+            let line_start = start.filter(|&start| start < self.text.len())?;
+            let end = self.line_end(line_start);
+            start = Some(end + 1);
+            Some((i, &self.text[line_start..end]))
+        })
+    }
+
+    /// The lines before line `index`, the nearest first, found in one pass
+    /// over the text. Line `len()` stands for the end of the text.
+    pub(crate) fn backward(&self, index: usize) -> impl Iterator<Item = (usize, &[u8])> {
+        let mut end = match self.start(index) {
+            Some(start) => start.checked_sub(1),
+            None if index > 0 && index == self.len() => {
+                // The last line may not end with a line break
+                Some(self.text.len() - usize::from(self.text.ends_with(b"\n")))
+            }
+            None => None,
+        };
+        (0..index).rev().map_while(move |i| {
+            let line_end = end?;
+            let start = self.line_start(line_end);
+            end = start.checked_sub(1);
+            Some((i, &self.text[start..line_end]))
+        })
+    }
+
+    /// Where line `index` starts.
+    fn start(&self, index: usize) -> Option<usize> {
+        self.find_to(index);
+        let mut start = {
+            let found = self.index.borrow();
+            if index >= found.len {
+                return None;
+            }
+            found.offsets[index / STRIDE]
+        };
+        for _ in 0..index % STRIDE {
+            start = self.line_end(start) + 1;
+        }
+        Some(start)
+    }
+
+    /// Where the line going on at `start` ends, before its line break.
+    fn line_end(&self, start: usize) -> usize {
+        let end = memchr::memchr(b'\n', &self.text[start..]).map_or(self.text.len(), |i| start + i);
+        #[cfg(test)]
+        self.scanned.set(self.scanned.get() + end - start);
+        end
+    }
+
+    /// Where the line ending at `end` starts.
+    fn line_start(&self, end: usize) -> usize {
+        let start = memchr::memrchr(b'\n', &self.text[..end]).map_or(0, |i| i + 1);
+        #[cfg(test)]
+        self.scanned.set(self.scanned.get() + end - start);
+        start
+    }
+
+    /// The number of lines, which finds all of them.
+    pub(crate) fn len(&self) -> usize {
+        self.find_to(usize::MAX);
+        self.index.borrow().len
+    }
+
+    /// The number of lines found so far, and whether that is all of them.
+    pub(crate) fn found(&self) -> (usize, bool) {
+        let index = self.index.borrow();
+        (index.len, index.complete)
+    }
+
+    /// Finds the lines up to line `line`, or to the end.
+    fn find_to(&self, line: usize) {
+        let mut index = self.index.borrow_mut();
+        while !index.complete && index.len <= line {
+            if index.end == self.text.len() {
+                index.complete = true;
+                break;
+            }
+            if index.len.is_multiple_of(STRIDE) {
+                let end = index.end;
+                index.offsets.push(end);
+            }
+            index.len += 1;
+            index.end = (self.line_end(index.end) + 1).min(self.text.len());
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/test_lines.rs"]
+mod tests;
@@ -1,48 +1,270 @@
-use arboard::Clipboard;
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{BufReader, Write};
 
+use args::{Args, Source, USAGE};
+use clipboard::{Backend, Clipboard};
+use compression::{Compression, decompress};
+use config::Config;
 pub use document::Document;
+use document::Tokenizer;
 use editor::Editor;
 pub use editor::Position;
 pub use editor::SearchDirection;
+use encoding::Encoding;
+use error::Error;
+use history::History;
 pub use row::Row;
 pub use terminal::Terminal;
+use tmux::Tmux;
 
+mod ansi;
+mod args;
+mod backend;
+mod clipboard;
+mod command;
+mod compression;
+mod config;
 mod document;
 mod editor;
+mod encoding;
+mod error;
 mod highlighting;
+mod history;
+mod lines;
+mod output;
 mod row;
+#[cfg(test)]
+mod screen;
+mod structure;
 mod terminal;
+mod tmux;
+mod tree;
 
 mod tokenizer;
 
-fn main() -> std::io::Result<()> {
-    let args: Vec<String> = std::env::args().collect();
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{e}");
+        std::process::exit(e.exit_code());
+    }
+}
 
-    let document = if let Some(file_name) = args.get(1) {
-        let reader = BufReader::new(File::open(file_name)?);
-        Document::new(reader)?
-    } else {
-        let stdin = std::io::stdin();
-        let lines = BufReader::new(stdin.lock());
-        Document::new(lines)?
+fn run() -> Result<(), Error> {
+    let args = Args::parse(std::env::args().skip(1)).map_err(Error::Usage)?;
+    if args.help {
+        print!("{USAGE}");
+        return Ok(());
+    }
+    if args.version {
+        println!("lcp {}", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+    let preset = Config::load()
+        .and_then(|config| config.preset(args.preset.as_deref()))
+        .map_err(Error::Config)?;
+
+    let history = History::default_path().map(History::new);
+    // What was copied before, when that is what's shown.
+    let mut history_entries = Vec::new();
+
+    // The documents to switch between, with the names of their files.
+    let mut documents = match &args.source {
+        Source::Files(file_names) => file_names
+            .iter()
+            .map(|file_name| Ok((Some(file_name.clone()), read_file(file_name)?)))
+            .collect::<Result<Vec<_>, Error>>()?,
+        Source::Stdin => vec![(None, read_file("-")?)],
+        Source::Tmux(pane) => {
+            let read_error = |e| Error::Io("the tmux pane".to_string(), e);
+            let capture = Tmux::new()
+                .capture_pane(pane.as_deref())
+                .map_err(read_error)?;
+            vec![(None, Document::new(capture.as_slice()).map_err(read_error)?)]
+        }
+        Source::History => {
+            let Some(history) = &history else {
+                return Err(Error::Usage(
+                    "Could not find a data directory for the history.".to_string(),
+                ));
+            };
+            let read_error = |e| Error::Io("the history".to_string(), e);
+            history_entries = history.entries().map_err(read_error)?;
+            let rendered = history::render(&history_entries, history::now());
+            let mut document = Document::new(rendered.as_bytes()).map_err(read_error)?;
+            document.update_tokenizer(Tokenizer::Line);
+            vec![(None, document)]
+        }
     };
 
-    if document.is_empty() {
-        panic!("You must construct additional pylons")
+    for (_, document) in documents.iter_mut() {
+        let encoding = args
+            .encoding
+            .unwrap_or_else(|| Encoding::detect(document.bytes()));
+        if encoding != Encoding::Utf8 {
+            document.set_encoding(encoding);
+        }
     }
 
-    let clipboard = Clipboard::new().expect("Failed to initialize clipboard");
+    if let Some(tokenizer) = &args.tokenizer {
+        for (_, document) in documents.iter_mut() {
+            document.update_tokenizer(tokenizer.clone());
+        }
+    }
+    if let Some(tab_width) = args.tab_width {
+        for (_, document) in documents.iter_mut() {
+            document.set_tab_width(tab_width);
+        }
+    }
 
-    let terminal = Terminal::new(None).expect("Failed to initialize terminal");
+    if documents.iter().all(|(_, document)| document.is_blank()) {
+        if args.allow_empty {
+            return Ok(());
+        }
+        return Err(Error::Empty(
+            match args.source {
+                Source::History => "Nothing has been copied yet.",
+                _ => "Nothing to copy: the input is empty.",
+            }
+            .to_string(),
+        ));
+    }
 
-    match Editor::new(document, Some(clipboard), terminal)
-        .expect("Failed to read input.")
-        .run()
-    {
-        Ok(_) => {}
-        Err(e) => println!("Error while running program: {e}"),
+    let clipboards = clipboards(args.clipboard, &args.source)?;
+
+    let terminal = terminal_backend()
+        .and_then(Terminal::new)
+        .map_err(Error::Terminal)?;
+
+    let mut documents = documents.into_iter();
+    let Some((name, document)) = documents.next() else {
+        unreachable!("there is a document for every source");
+    };
+    let mut editor = Editor::new(document, clipboards, terminal).map_err(Error::Terminal)?;
+    if let Some(name) = name {
+        editor.set_name(name);
+    }
+    for (name, document) in documents {
+        editor.add_buffer(name.unwrap_or_default(), document);
+    }
+    editor.set_commands(preset.commands);
+    editor.set_output_format(args.output);
+    editor.set_line_numbers(args.line_numbers);
+    editor.set_wrap(args.wrap);
+    if let Some(header) = args.header {
+        editor.set_header(header);
+    }
+    editor.set_copies(
+        history_entries
+            .iter()
+            .map(|entry| entry.text.clone())
+            .collect(),
+    );
+    if let Some(line) = args.start_line {
+        editor.jump_to_line(line);
+    } else if let Source::Tmux(_) = args.source {
+        editor.jump_to_bottom();
+    }
+    if let Some(query) = &args.start_search {
+        editor.start_search(query);
+    }
+    editor.run()?;
+
+    let copied = editor.copied().map(str::to_string);
+    // An entry of the history copied again keeps where it was copied from.
+    let copied_from = history_entries
+        .iter()
+        .find(|entry| copied.as_deref() == Some(entry.text.as_str()))
+        .map(|entry| entry.source.clone())
+        .or_else(|| editor.name().map(str::to_string))
+        .unwrap_or_else(|| args.source.as_str());
+    let exec_command = editor.exec_command().map(str::to_string);
+    let copy_error = editor.copy_error().map(str::to_string);
+    // Restore the terminal before writing to it or handing it over.
+    drop(editor);
+
+    if let Some(text) = &copied {
+        if let Some(history) = &history
+            && let Err(e) = history.push(&copied_from, text)
+        {
+            eprintln!("Failed to save history: {e}");
+        }
+        if let Some(path) = &args.output_file {
+            write_output(path, text)?;
+        }
+    }
+
+    if let Some(command_line) = exec_command {
+        let e = command::exec(&command_line);
+        return Err(Error::Command(command_line, e));
+    }
+    if let Some(e) = copy_error {
+        return Err(Error::Clipboard(e));
     }
     Ok(())
 }
+
+/// Reads the file at `path`, or stdin for `-`, decompressing it if need be.
+/// Plain files are mapped into memory rather than read.
+fn read_file(path: &str) -> Result<Document, Error> {
+    if path == "-" {
+        let stdin = std::io::stdin();
+        return decompress(BufReader::new(stdin.lock()))
+            .and_then(Document::new)
+            .map_err(|e| Error::Io("stdin".to_string(), e));
+    }
+    let read_error = |e| Error::Io(path.to_string(), e);
+    let file = File::open(path).map_err(read_error)?;
+    // Pipes like `<(kubectl logs ...)` can't be mapped
+    if !file.metadata().map_err(read_error)?.is_file() {
+        return decompress(BufReader::new(file))
+            .and_then(Document::new)
+            .map_err(read_error);
+    }
+    let document = Document::open(&file).map_err(read_error)?;
+    match Compression::detect(document.bytes()) {
+        None => Ok(document),
+        Some(_) => decompress(document.bytes())
+            .and_then(Document::new)
+            .map_err(read_error),
+    }
+}
+
+/// The terminal to draw on, through the library lcp was built with:
+/// crossterm when both are.
+fn terminal_backend() -> std::io::Result<impl backend::Backend> {
+    #[cfg(feature = "crossterm")]
+    return backend::Crossterm::new();
+    #[cfg(all(feature = "termion", not(feature = "crossterm")))]
+    return backend::Termion::new();
+}
+
+/// The clipboards `backend` stands for when reading from `source`.
+fn clipboards(backend: Backend, source: &Source) -> Result<Vec<Clipboard>, Error> {
+    let system = || {
+        arboard::Clipboard::new()
+            .map(Clipboard::System)
+            .map_err(|e| Error::Clipboard(e.to_string()))
+    };
+    Ok(match (backend, source) {
+        (Backend::None, _) => Vec::new(),
+        (Backend::System, _) => vec![system()?],
+        (Backend::Tmux, _) => vec![Clipboard::Tmux(Tmux::new())],
+        // The system clipboard is often unavailable where tmux runs (e.g. over ssh),
+        // so the tmux buffer is the one that must work.
+        (Backend::Auto, Source::Tmux(_)) => {
+            let mut clipboards = vec![Clipboard::Tmux(Tmux::new())];
+            clipboards.extend(system().ok());
+            clipboards
+        }
+        (Backend::Auto, _) => vec![system()?],
+    })
+}
+
+/// Writes copied text to the file at `path`, or stdout for `-`.
+fn write_output(path: &str, text: &str) -> Result<(), Error> {
+    match path {
+        "-" => writeln!(std::io::stdout(), "{text}"),
+        _ => std::fs::write(path, format!("{text}\n")),
+    }
+    .map_err(|e| Error::Write(path.to_string(), e))
+}
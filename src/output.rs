@@ -0,0 +1,63 @@
+use crate::command::shell_quote;
+
+/// How copied text is transformed before it goes to the clipboard.
+///
+/// Each line of the selection is an item. Items are trimmed, shell-quoted and
+/// put into the template in that order, then joined or turned into a JSON array.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct OutputFormat {
+    /// Joins items, `\n` when not set.
+    pub(crate) separator: Option<String>,
+    pub(crate) trim: bool,
+    pub(crate) quote: bool,
+    pub(crate) json: bool,
+    /// Each item replaces `{}` in the template.
+    pub(crate) template: Option<String>,
+}
+
+impl OutputFormat {
+    pub(crate) fn apply(&self, text: &str) -> String {
+        let items: Vec<String> = text
+            .lines()
+            .map(|line| {
+                let mut item = if self.trim { line.trim() } else { line }.to_string();
+                if self.quote {
+                    item = shell_quote(&item);
+                }
+                if let Some(template) = &self.template {
+                    item = template.replace("{}", &item);
+                }
+                item
+            })
+            .collect();
+        if self.json {
+            let items: Vec<String> = items.iter().map(|item| json_string(item)).collect();
+            format!("[{}]", items.join(", "))
+        } else {
+            items.join(self.separator.as_deref().unwrap_or("\n"))
+        }
+    }
+}
+
+/// `s` as a JSON string literal.
+pub(crate) fn json_string(s: &str) -> String {
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if c.is_control() => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+#[cfg(test)]
+#[path = "tests/test_output.rs"]
+mod tests;
@@ -1,26 +1,154 @@
+use crate::ansi::{self, Bg, Color};
 use crate::document::Tokenizer;
-use crate::highlighting::{HighlightedText, TextMode};
-use crate::{Position, SearchDirection, highlighting};
-use std::cmp;
+use crate::highlighting::TextMode;
+use crate::{Position, SearchDirection};
+use std::borrow::Cow;
 use std::cmp::Ordering;
-use termion::color;
 use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
-const HIGHLIGHTING_COLOR: color::LightWhite = color::LightWhite;
+const HIGHLIGHTING_COLOR: Color = Color::LIGHT_WHITE;
 
+/// How many columns apart tab stops are, unless told otherwise.
+pub(crate) const TAB_WIDTH: usize = 8;
+
+/// The graphemes `start..start + len` of a row.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct Token {
     pub(crate) start: usize,
     pub(crate) len: usize,
 }
 
+/// A row of the document.
+///
+/// Columns are grapheme indices, which is what tokens, positions and
+/// highlighting use. Only drawing counts display width, where a wide
+/// grapheme like 日 takes two columns of the screen.
+///
+/// Columns past the end of the row are virtual: visual block mode can select
+/// them as if the row went on with spaces, without the row storing any.
+///
+/// Tabs are only expanded when drawn, to the next tab stop from the start of
+/// the row, so that copied text keeps them.
 #[derive(Debug, Eq, PartialEq)]
 pub struct Row {
     pub(crate) string: String,
-    highlighting: Vec<highlighting::Type>,
     pub(crate) tokens: Vec<Token>,
-    pub is_highlighted: bool,
+    /// The number of graphemes.
     pub(crate) len: usize,
+    /// The byte offset of every grapheme.
+    graphemes: Vec<usize>,
+    /// How every grapheme stands out, if any of them does.
+    emphasis: Vec<Emphasis>,
+    tab_width: usize,
+}
+
+/// How a character struck over with a backspace stands out, which is how
+/// `man` makes text bold or underlined without colors.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum Emphasis {
+    #[default]
+    Plain,
+    /// Struck over with itself.
+    Bold,
+    /// Struck over with `_`.
+    Underline,
+}
+
+impl Emphasis {
+    fn start(self) -> &'static str {
+        match self {
+            Emphasis::Plain => "",
+            Emphasis::Bold => ansi::BOLD,
+            Emphasis::Underline => ansi::UNDERLINE,
+        }
+    }
+
+    fn end(self) -> &'static str {
+        match self {
+            Emphasis::Plain => "",
+            Emphasis::Bold => ansi::NO_BOLD,
+            Emphasis::Underline => ansi::NO_UNDERLINE,
+        }
+    }
+}
+
+/// What a terminal shows for `line`, as its characters and how they stand
+/// out: a backspace steps back over the last character, a carriage return
+/// goes back to the start of the line, and what follows is written over what
+/// is there. `None` when `line` has neither.
+///
+/// This makes `man` pages and progress bars, which redraw the line after a
+/// carriage return, read as they did on the screen.
+pub(crate) fn overstrike(line: &str) -> Option<Vec<(char, Emphasis)>> {
+    if !line.contains(['\u{8}', '\r']) {
+        return None;
+    }
+    let mut cells: Vec<(char, Emphasis)> = Vec::new();
+    let mut column = 0;
+    // How many of the characters after the cursor were stepped back over
+    // with a backspace, rather than a carriage return.
+    let mut struck: usize = 0;
+    for c in line.chars() {
+        match c {
+            '\u{8}' if column > 0 => {
+                column -= 1;
+                struck += 1;
+            }
+            '\u{8}' => {}
+            '\r' => {
+                column = 0;
+                struck = 0;
+            }
+            c => {
+                let cell = match cells.get(column) {
+                    Some(&(old, _)) if struck > 0 && old == c => (c, Emphasis::Bold),
+                    Some(&('_', _)) if struck > 0 => (c, Emphasis::Underline),
+                    Some(&(old, _)) if struck > 0 && c == '_' => (old, Emphasis::Underline),
+                    _ => (c, Emphasis::Plain),
+                };
+                match cells.get_mut(column) {
+                    Some(old) => *old = cell,
+                    None => cells.push(cell),
+                }
+                column += 1;
+                struck = struck.saturating_sub(1);
+            }
+        }
+    }
+    Some(cells)
+}
+
+/// The start of `text` that fits in `width` columns, cut between graphemes.
+pub(crate) fn truncate(text: &str, width: usize) -> &str {
+    let mut used = 0;
+    for (i, grapheme) in text.grapheme_indices(true) {
+        used += grapheme.width();
+        if used > width {
+            return &text[..i];
+        }
+    }
+    text
+}
+
+/// How `grapheme` is drawn. Control characters would move the cursor or
+/// change colors, so they are drawn in caret notation like `^[`, or as
+/// `<9B>` for those without one.
+fn display(grapheme: &str) -> Cow<'_, str> {
+    if !grapheme.chars().any(char::is_control) {
+        return Cow::Borrowed(grapheme);
+    }
+    Cow::Owned(
+        grapheme
+            .chars()
+            .map(|c| match u32::from(c) {
+                control @ 0..=0x1f => format!("^{}", char::from_u32(control + 0x40).unwrap()),
+                0x7f => "^?".to_string(),
+                control if c.is_control() => format!("<{control:02X}>"),
+                _ => c.to_string(),
+            })
+            .collect(),
+    )
 }
 
 fn mk_tok_and_update_start(slice: &str, tok_s: &str, start: usize) -> (Token, usize) {
@@ -34,7 +162,8 @@ fn mk_tok_and_update_start(slice: &str, tok_s: &str, start: usize) -> (Token, us
     (tok, start + div_len + tok_len)
 }
 
-pub(crate) fn mk_tokens(slice: &str, tokenizer: &Tokenizer) -> Vec<Token> {
+/// Splits `slice` into tokens, with byte offsets.
+fn mk_byte_tokens(slice: &str, tokenizer: &Tokenizer) -> Vec<Token> {
     let mut tokens = vec![];
     let mut start = 0;
     match &tokenizer {
@@ -52,68 +181,205 @@ pub(crate) fn mk_tokens(slice: &str, tokenizer: &Tokenizer) -> Vec<Token> {
                 start = new_start;
             }
         }
+        Tokenizer::Line => {
+            let line = slice.trim();
+            if !line.is_empty() {
+                let (tok, _) = mk_tok_and_update_start(slice, line, 0);
+                tokens.push(tok);
+            }
+        }
+        Tokenizer::Regex(regex) => {
+            for tok in regex.find_iter(slice).filter(|tok| !tok.is_empty()) {
+                tokens.push(Token {
+                    start: tok.start(),
+                    len: tok.len(),
+                });
+            }
+        }
     }
     tokens
 }
 
+/// Splits `slice` into tokens, with grapheme offsets.
+pub(crate) fn mk_tokens(slice: &str, tokenizer: &Tokenizer) -> Vec<Token> {
+    let graphemes: Vec<usize> = slice.grapheme_indices(true).map(|(i, _)| i).collect();
+    let index = |byte: usize| graphemes.partition_point(|&start| start < byte);
+    mk_byte_tokens(slice, tokenizer)
+        .into_iter()
+        .map(|token| {
+            let start = index(token.start);
+            Token {
+                start,
+                len: index(token.start + token.len) - start,
+            }
+        })
+        .collect()
+}
+
 impl Row {
+    /// Makes a row of the line `slice`, as it shows on a terminal when it has
+    /// backspaces or carriage returns.
     pub(crate) fn new(slice: &str, tokenizer: &Tokenizer) -> Self {
+        let (string, emphasis): (String, Vec<Emphasis>) = match overstrike(slice) {
+            None => (String::from(slice), Vec::new()),
+            Some(cells) => cells.into_iter().unzip(),
+        };
+        let graphemes: Vec<usize> = string.grapheme_indices(true).map(|(i, _)| i).collect();
+        // From the emphasis of every char to that of every grapheme
+        let emphasis = match emphasis.iter().all(|&emphasis| emphasis == Emphasis::Plain) {
+            true => Vec::new(),
+            false => {
+                let chars: Vec<usize> = string.char_indices().map(|(i, _)| i).collect();
+                graphemes
+                    .iter()
+                    .map(|&start| emphasis[chars.partition_point(|&i| i < start)])
+                    .collect()
+            }
+        };
         Self {
-            string: String::from(slice),
-            highlighting: Vec::new(),
-            tokens: mk_tokens(slice, tokenizer),
-            is_highlighted: false,
-            len: slice.graphemes(true).count(),
+            tokens: mk_tokens(&string, tokenizer),
+            len: graphemes.len(),
+            string,
+            graphemes,
+            emphasis,
+            tab_width: TAB_WIDTH,
         }
     }
+
+    /// Sets how many columns apart tab stops are.
+    pub(crate) fn with_tab_width(mut self, tab_width: usize) -> Self {
+        self.tab_width = tab_width.max(1);
+        self
+    }
 }
 
 impl Row {
-    pub(crate) fn whitespace_pad(&mut self, total_len: usize) {
-        let whitespace_len = total_len.saturating_sub(self.string.len());
-        if whitespace_len > 0 {
-            let whitespace = vec![" "; whitespace_len].join("");
-            self.string.push_str(&whitespace);
-            self.len = whitespace_len;
+    /// The byte offset of grapheme `index`, or the end of the row.
+    pub(crate) fn byte(&self, index: usize) -> usize {
+        self.graphemes
+            .get(index)
+            .copied()
+            .unwrap_or(self.string.len())
+    }
+
+    /// The grapheme the byte offset `byte` is in.
+    pub(crate) fn grapheme(&self, byte: usize) -> usize {
+        self.graphemes
+            .partition_point(|&start| start <= byte)
+            .saturating_sub(1)
+    }
+
+    /// The graphemes `start..end`.
+    pub(crate) fn slice(&self, start: usize, end: usize) -> &str {
+        let end = self.byte(end);
+        &self.string[self.byte(start).min(end)..end]
+    }
+
+    /// The row without trailing whitespace.
+    pub(crate) fn text(&self) -> &str {
+        self.string.trim_end()
+    }
+
+    /// How `grapheme` is drawn when it starts at screen column `column` of
+    /// the row. A tab takes the columns to the next tab stop.
+    fn draw<'a>(&self, grapheme: &'a str, column: usize) -> Cow<'a, str> {
+        match grapheme {
+            "\t" => Cow::Owned(" ".repeat(self.tab_width - column % self.tab_width)),
+            _ => display(grapheme),
         }
     }
 
+    /// The screen column grapheme `index` starts at, a virtual column taking
+    /// one.
+    pub(crate) fn column(&self, index: usize) -> usize {
+        let column = self
+            .slice(0, index)
+            .graphemes(true)
+            .fold(0, |column, grapheme| {
+                column + self.draw(grapheme, column).width()
+            });
+        column + index.saturating_sub(self.len)
+    }
+
+    /// The columns the graphemes `start..end` take on the screen.
+    pub(crate) fn width(&self, start: usize, end: usize) -> usize {
+        self.column(end).saturating_sub(self.column(start))
+    }
+
     pub(crate) fn token(&self, index: usize) -> Option<&Token> {
         self.tokens.get(index)
     }
 
-    pub(crate) fn render(&self, start: usize, end: usize) -> String {
-        let end = cmp::min(end, self.string.len());
-        let start = cmp::min(start, end);
+    /// Splits the row into screen lines of at most `width` columns, as the
+    /// ranges of graphemes to render. The last one goes on with virtual
+    /// columns to the edge of the screen.
+    pub(crate) fn wrap(&self, width: usize) -> Vec<(usize, usize)> {
+        let width = width.max(1);
+        let mut lines = Vec::new();
+        let (mut start, mut end, mut line_width, mut column) = (0, 0, 0, 0);
+        for grapheme in self.text().graphemes(true) {
+            let grapheme_width = self.draw(grapheme, column).width();
+            if line_width > 0 && line_width + grapheme_width > width {
+                lines.push((start, end));
+                start = end;
+                line_width = 0;
+            }
+            end += 1;
+            line_width += grapheme_width;
+            column += grapheme_width;
+        }
+        lines.push((start, end + width.saturating_sub(line_width)));
+        lines
+    }
+
+    /// Renders the graphemes from `start` on that fit in `width` columns,
+    /// and no further than `end`, with the graphemes `highlighted` highlighted.
+    /// Of the virtual columns, only highlighted ones are drawn.
+    pub(crate) fn render(
+        &self,
+        start: usize,
+        end: usize,
+        width: usize,
+        highlighted: (usize, usize),
+    ) -> String {
         let mut result = String::new();
-        let mut current_highlighting = &highlighting::Type::None;
+        let mut current_highlighting = false;
+        let mut current_emphasis = Emphasis::Plain;
+        let mut used = 0;
+        let mut column = self.column(start);
+        let virtual_columns = (self.len.max(start)..highlighted.1.min(end)).map(|_| " ");
         for (index, c) in self
-            .string
-            .chars()
+            .slice(start, end)
+            .graphemes(true)
+            .chain(virtual_columns)
             .enumerate()
-            .skip(start)
-            .take(end - start)
+            .map(|(i, grapheme)| (start + i, grapheme))
         {
-            let highlighting_type = self
-                .highlighting
-                .get(index)
-                .unwrap_or(&highlighting::Type::None);
-            if highlighting_type != current_highlighting {
-                current_highlighting = highlighting_type;
-                let start_highlight = match highlighting_type {
-                    highlighting::Type::None => format!("{}", color::Bg(color::Reset)),
-                    highlighting::Type::Highlighted => format!("{}", color::Bg(HIGHLIGHTING_COLOR)),
+            let drawn = self.draw(c, column);
+            column += drawn.width();
+            used += drawn.width();
+            if used > width {
+                break;
+            }
+            let is_highlighted = (highlighted.0..highlighted.1).contains(&index);
+            if is_highlighted != current_highlighting {
+                current_highlighting = is_highlighted;
+                let start_highlight = match is_highlighted {
+                    false => Bg(Color::Reset).to_string(),
+                    true => Bg(HIGHLIGHTING_COLOR).to_string(),
                 };
                 result.push_str(&start_highlight);
             }
-            if c == '\t' {
-                result.push(' ');
-            } else {
-                result.push(c);
+            let emphasis = self.emphasis.get(index).copied().unwrap_or_default();
+            if emphasis != current_emphasis {
+                result.push_str(current_emphasis.end());
+                result.push_str(emphasis.start());
+                current_emphasis = emphasis;
             }
+            result.push_str(&drawn);
         }
-        let end_highlight = format!("{}", color::Bg(color::Reset));
-        result.push_str(&end_highlight);
+        result.push_str(current_emphasis.end());
+        result.push_str(&Bg(Color::Reset).to_string());
         result
     }
 
@@ -123,7 +389,7 @@ impl Row {
                 0 => usize::MAX,
                 n => n.saturating_sub(1),
             },
-            TextMode::Visual(Position { longest_row, .. }) => longest_row,
+            TextMode::Visual(Position { longest_row, .. }, _) => longest_row,
             TextMode::Search(_) => self.len.saturating_sub(1),
         }
     }
@@ -132,53 +398,18 @@ impl Row {
         if at > self.len || query.is_empty() {
             return None;
         }
-        let (start, end) = match direction {
-            SearchDirection::Forward => (at, self.len),
-            SearchDirection::Backward => (0, at),
-        };
-
-        let substring: String = self.string.chars().skip(start).take(end - start).collect();
+        let at = self.byte(at);
         match direction {
-            SearchDirection::Forward => substring.find(query),
-            SearchDirection::Backward => substring.rfind(query),
-        }
-        .map(|i| i + start)
-    }
-
-    pub(crate) fn highlight(&mut self, text: &HighlightedText) {
-        self.highlighting = vec![highlighting::Type::None; self.string.len()];
-        match text.mode {
-            TextMode::Token => {
-                if let Some(tok) = self.token(text.position.x) {
-                    for i in tok.start..tok.start + tok.len {
-                        if let Some(highlighting) = self.highlighting.get_mut(i) {
-                            *highlighting = highlighting::Type::Highlighted;
-                        };
-                    }
-                }
-            }
-            TextMode::Search(maybe_len) => {
-                if let Some(len) = maybe_len {
-                    for i in text.position.x..text.position.x + len {
-                        if let Some(highlighting) = self.highlighting.get_mut(i) {
-                            *highlighting = highlighting::Type::Highlighted;
-                        };
-                    }
-                }
-            }
-            TextMode::Visual(start_pos) => {
-                let (start, end) = switch_start_end(start_pos.x, text.position.x);
-                for i in start..end + 1 {
-                    if let Some(highlighting) = self.highlighting.get_mut(i) {
-                        *highlighting = highlighting::Type::Highlighted;
-                    };
-                }
-            }
+            SearchDirection::Forward => self.string[at..].find(query).map(|i| i + at),
+            SearchDirection::Backward => self.string[..at].rfind(query),
         }
+        .map(|i| self.grapheme(i))
     }
 
-    pub(crate) fn unhighlight(&mut self) {
-        self.highlighting = vec![];
+    /// The number of graphemes without trailing whitespace.
+    pub(crate) fn text_len(&self) -> usize {
+        let end = self.text().len();
+        self.graphemes.partition_point(|&start| start < end)
     }
 }
 
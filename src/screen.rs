@@ -0,0 +1,240 @@
+use std::fmt;
+use std::fs;
+use std::iter::Peekable;
+use std::path::PathBuf;
+use std::str::Chars;
+
+use unicode_width::UnicodeWidthChar;
+
+/// A color, as set by an SGR sequence.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Color {
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) struct Style {
+    pub(crate) fg: Option<Color>,
+    pub(crate) bg: Option<Color>,
+    pub(crate) bold: bool,
+    pub(crate) underline: bool,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct Cell {
+    /// Empty for the second column of a wide character.
+    pub(crate) text: String,
+    pub(crate) style: Style,
+}
+
+impl Cell {
+    fn blank() -> Self {
+        Self {
+            text: " ".to_string(),
+            style: Style::default(),
+        }
+    }
+}
+
+/// A terminal screen in memory, for tests to see what lcp drew. It follows
+/// the cursor movements, clears and colors lcp sends, and nothing more.
+#[derive(Clone, Debug)]
+pub(crate) struct Screen {
+    cells: Vec<Vec<Cell>>,
+    /// The column and row of the cursor.
+    cursor: (usize, usize),
+    style: Style,
+}
+
+/// Screens are equal when they show the same, wherever their cursors are.
+impl PartialEq for Screen {
+    fn eq(&self, other: &Self) -> bool {
+        self.cells == other.cells
+    }
+}
+
+impl Screen {
+    pub(crate) fn new(width: u16, height: u16) -> Self {
+        Self {
+            cells: vec![vec![Cell::blank(); usize::from(width)]; usize::from(height)],
+            cursor: (0, 0),
+            style: Style::default(),
+        }
+    }
+
+    pub(crate) fn cell(&self, x: usize, y: usize) -> &Cell {
+        &self.cells[y][x]
+    }
+
+    /// The text of row `y`, without the blanks at its end.
+    pub(crate) fn line(&self, y: usize) -> String {
+        let line: String = self.cells[y]
+            .iter()
+            .map(|cell| cell.text.as_str())
+            .collect();
+        line.trim_end().to_string()
+    }
+
+    /// Interprets what was written to the terminal.
+    pub(crate) fn feed(&mut self, output: &str) {
+        let mut chars = output.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\u{1b}' if chars.next_if_eq(&'[').is_some() => self.csi(&mut chars),
+                // Not something lcp sends
+                '\u{1b}' => {
+                    chars.next();
+                }
+                '\r' => self.cursor.0 = 0,
+                '\n' => self.cursor.1 = (self.cursor.1 + 1).min(self.cells.len() - 1),
+                c => self.print(c),
+            }
+        }
+    }
+
+    fn print(&mut self, c: char) {
+        let (x, y) = self.cursor;
+        let width = c.width().unwrap_or(0);
+        let row = &mut self.cells[y];
+        if width == 0 {
+            // Combining characters join the one before
+            if let Some(cell) = x.checked_sub(1).and_then(|x| row.get_mut(x)) {
+                cell.text.push(c);
+            }
+            return;
+        }
+        // Like lcp, nothing is drawn past the edge of the screen
+        if x + width > row.len() {
+            return;
+        }
+        row[x] = Cell {
+            text: c.to_string(),
+            style: self.style,
+        };
+        if width == 2 {
+            row[x + 1] = Cell {
+                text: String::new(),
+                style: self.style,
+            };
+        }
+        self.cursor.0 += width;
+    }
+
+    /// A control sequence, after its `ESC [`.
+    fn csi(&mut self, chars: &mut Peekable<Chars>) {
+        let mut parameters = String::new();
+        while let Some(c) = chars.next_if(|c| ('\u{30}'..='\u{3f}').contains(c)) {
+            parameters.push(c);
+        }
+        let Some(command) = chars.next() else {
+            return;
+        };
+        let numbers: Vec<u16> = parameters
+            .split(';')
+            .map(|number| number.parse().unwrap_or(0))
+            .collect();
+        let (width, height) = (self.cells[0].len(), self.cells.len());
+        match command {
+            'H' => {
+                let at = |i: usize| usize::from(numbers.get(i).copied().unwrap_or(1).max(1)) - 1;
+                self.cursor = (at(1).min(width - 1), at(0).min(height - 1));
+            }
+            'J' => self.cells = vec![vec![Cell::blank(); width]; height],
+            'K' => self.cells[self.cursor.1] = vec![Cell::blank(); width],
+            'm' => self.sgr(&numbers),
+            // Showing and hiding the cursor
+            _ => {}
+        }
+    }
+
+    /// Select Graphic Rendition: colors and styles.
+    fn sgr(&mut self, numbers: &[u16]) {
+        let mut numbers = numbers.iter().copied();
+        while let Some(number) = numbers.next() {
+            match number {
+                0 => self.style = Style::default(),
+                1 => self.style.bold = true,
+                22 => self.style.bold = false,
+                4 => self.style.underline = true,
+                24 => self.style.underline = false,
+                30..=37 => self.style.fg = Some(Color::Indexed(number as u8 - 30)),
+                90..=97 => self.style.fg = Some(Color::Indexed(number as u8 - 90 + 8)),
+                40..=47 => self.style.bg = Some(Color::Indexed(number as u8 - 40)),
+                100..=107 => self.style.bg = Some(Color::Indexed(number as u8 - 100 + 8)),
+                38 => self.style.fg = color(&mut numbers),
+                48 => self.style.bg = color(&mut numbers),
+                39 => self.style.fg = None,
+                49 => self.style.bg = None,
+                _ => {}
+            }
+        }
+    }
+}
+
+/// The color of a `38` or `48` SGR parameter, from the ones after it.
+fn color(numbers: &mut impl Iterator<Item = u16>) -> Option<Color> {
+    let mut next = || numbers.next().unwrap_or(0) as u8;
+    match next() {
+        5 => Some(Color::Indexed(next())),
+        2 => Some(Color::Rgb(next(), next(), next())),
+        _ => None,
+    }
+}
+
+/// Every row as `|` and its text, followed, when some of it stands out, by
+/// `:` and a mark under every cell: `^` for a background color, `*` for bold,
+/// `_` for underlined and `~` for a foreground color.
+impl fmt::Display for Screen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (y, row) in self.cells.iter().enumerate() {
+            writeln!(f, "|{}", self.line(y))?;
+            let marks: String = row
+                .iter()
+                .map(|cell| match cell.style {
+                    Style { bg: Some(_), .. } => '^',
+                    Style { bold: true, .. } => '*',
+                    Style {
+                        underline: true, ..
+                    } => '_',
+                    Style { fg: Some(_), .. } => '~',
+                    _ => ' ',
+                })
+                .collect();
+            let marks = marks.trim_end();
+            if !marks.is_empty() {
+                writeln!(f, ":{marks}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compares `actual` with the snapshot file `name`, or writes the file when
+/// `UPDATE_SNAPSHOTS` is set, to be reviewed like any other change.
+pub(crate) fn assert_snapshot(name: &str, actual: &str) {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "src/tests/snapshots", name]
+        .iter()
+        .collect();
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!(
+            "Failed to read {}: {e}. Run with UPDATE_SNAPSHOTS=1 to write it.",
+            path.display()
+        )
+    });
+    assert!(
+        expected == actual,
+        "The screen doesn't match {}. Run with UPDATE_SNAPSHOTS=1 to update it.\n\
+         Expected:\n{expected}\nActual:\n{actual}",
+        path.display()
+    );
+}
+
+#[cfg(test)]
+#[path = "tests/test_screen.rs"]
+mod tests;
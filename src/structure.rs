@@ -0,0 +1,429 @@
+use std::borrow::Cow;
+
+use crate::Document;
+
+const QUOTES: &[u8] = b"\"'`";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Kind {
+    Token,
+    Quoted,
+    Brackets,
+    Pair,
+    Subtree,
+    Line,
+    Paragraph,
+}
+
+impl Kind {
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            Kind::Token => "token",
+            Kind::Quoted => "quoted string",
+            Kind::Brackets => "brackets",
+            Kind::Pair => "key: value",
+            Kind::Subtree => "subtree",
+            Kind::Line => "line",
+            Kind::Paragraph => "paragraph",
+        }
+    }
+}
+
+/// A part of the document between two `(y, x)` positions, both inclusive,
+/// with `x` a byte offset in the row.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct Span {
+    pub(crate) kind: Kind,
+    pub(crate) start: (usize, usize),
+    pub(crate) end: (usize, usize),
+}
+
+impl Span {
+    fn new(kind: Kind, start: (usize, usize), end: (usize, usize)) -> Self {
+        Self { kind, start, end }
+    }
+
+    fn contains(&self, other: &Span) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    fn same_extent(&self, other: &Span) -> bool {
+        self.start == other.start && self.end == other.end
+    }
+}
+
+/// The token at `x` of row `y`.
+pub(crate) fn token(document: &Document, x: usize, y: usize) -> Option<Span> {
+    let row = document.row(y)?;
+    let token = row.token(x)?;
+    Some(Span::new(
+        Kind::Token,
+        (y, row.byte(token.start)),
+        (y, row.byte(token.start + token.len).saturating_sub(1)),
+    ))
+}
+
+/// The smallest structure around `span` that is bigger than it.
+pub(crate) fn expand(document: &Document, span: &Span) -> Option<Span> {
+    let mut candidates = Vec::new();
+    if span.start.0 == span.end.0 {
+        quoted(document, span, &mut candidates);
+        pair(document, span, &mut candidates);
+    }
+    brackets(document, span, &mut candidates);
+    if span.start.0 == span.end.0 {
+        line(document, span, &mut candidates);
+    }
+    subtrees(document, span, &mut candidates);
+    paragraph(document, span, &mut candidates);
+    // Candidates are pushed from the finest kind, so on a tie the finer one wins.
+    candidates
+        .into_iter()
+        .filter(|candidate| candidate.contains(span) && !candidate.same_extent(span))
+        .min_by_key(|candidate| size(document, candidate))
+}
+
+fn size(document: &Document, span: &Span) -> usize {
+    let (start_y, start_x) = span.start;
+    let (end_y, end_x) = span.end;
+    if start_y == end_y {
+        return end_x.saturating_sub(start_x) + 1;
+    }
+    let first = text_len(document, start_y).saturating_sub(start_x);
+    let middle: usize = (start_y + 1..end_y)
+        .map(|y| text_len(document, y) + 1)
+        .sum();
+    first + middle + end_x + 2
+}
+
+fn bytes(document: &Document, y: usize) -> Cow<'_, [u8]> {
+    match document.text(y) {
+        Cow::Borrowed(text) => Cow::Borrowed(text.as_bytes()),
+        Cow::Owned(text) => Cow::Owned(text.into_bytes()),
+    }
+}
+
+fn text_len(document: &Document, y: usize) -> usize {
+    bytes(document, y).len()
+}
+
+fn is_blank(document: &Document, y: usize) -> bool {
+    text_len(document, y) == 0
+}
+
+fn indent(document: &Document, y: usize) -> usize {
+    bytes(document, y)
+        .iter()
+        .take_while(|b| b.is_ascii_whitespace())
+        .count()
+}
+
+/// Whether row `y` is an item of a yaml sequence.
+fn is_item(document: &Document, y: usize) -> bool {
+    let bytes = &bytes(document, y)[indent(document, y)..];
+    bytes == b"-" || bytes.starts_with(b"- ")
+}
+
+/// `(y, x)` to the end of row `y` as a span.
+fn to_end_of_row(kind: Kind, start: (usize, usize), document: &Document, y: usize) -> Span {
+    Span::new(kind, start, (y, text_len(document, y).saturating_sub(1)))
+}
+
+/// The quoted strings of the row around `span`, with and without the quotes.
+fn quoted(document: &Document, span: &Span, candidates: &mut Vec<Span>) {
+    let (y, _) = span.start;
+    let bytes = bytes(document, y);
+    let mut i = 0;
+    while i < bytes.len() {
+        if !QUOTES.contains(&bytes[i]) {
+            i += 1;
+            continue;
+        }
+        let quote = bytes[i];
+        let mut j = i + 1;
+        while j < bytes.len() && (bytes[j] != quote || bytes[j - 1] == b'\\') {
+            j += 1;
+        }
+        if j == bytes.len() {
+            return;
+        }
+        if i + 1 < j {
+            candidates.push(Span::new(Kind::Quoted, (y, i + 1), (y, j - 1)));
+        }
+        candidates.push(Span::new(Kind::Quoted, (y, i), (y, j)));
+        i = j + 1;
+    }
+}
+
+/// A `key: value` pair on the row around `span`. Inside brackets pairs are
+/// separated by commas, otherwise the pair is the whole row.
+fn pair(document: &Document, span: &Span, candidates: &mut Vec<Span>) {
+    let (y, start_x) = span.start;
+    let (_, end_x) = span.end;
+    let bytes = bytes(document, y);
+
+    let mut start = None;
+    let mut depth = 0;
+    for x in (0..start_x.min(bytes.len())).rev() {
+        match bytes[x] {
+            b')' | b']' | b'}' => depth += 1,
+            b'(' | b'[' | b'{' if depth == 0 => {
+                start = Some(x + 1);
+                break;
+            }
+            b'(' | b'[' | b'{' => depth -= 1,
+            b',' if depth == 0 => {
+                start = Some(x + 1);
+                break;
+            }
+            _ => {}
+        }
+    }
+    let (start, end) = match start {
+        None => {
+            let mut start = indent(document, y);
+            if is_item(document, y) {
+                start = (start + 1..bytes.len())
+                    .find(|&x| !bytes[x].is_ascii_whitespace())
+                    .unwrap_or(bytes.len());
+            }
+            (start, bytes.len())
+        }
+        Some(start) => {
+            // Start from the beginning of the pair, brackets in `span` count too.
+            let mut depth = 0;
+            let mut end = bytes.len();
+            for (x, &b) in bytes.iter().enumerate().skip(start) {
+                match b {
+                    b'(' | b'[' | b'{' => depth += 1,
+                    b')' | b']' | b'}' | b',' if depth == 0 && x > end_x => {
+                        end = x;
+                        break;
+                    }
+                    b')' | b']' | b'}' => depth -= 1,
+                    _ => {}
+                }
+            }
+            (start, end)
+        }
+    };
+    let Some(segment) = bytes.get(start..end) else {
+        return;
+    };
+    let leading = segment
+        .iter()
+        .take_while(|b| b.is_ascii_whitespace())
+        .count();
+    let trailing = segment
+        .iter()
+        .rev()
+        .take_while(|b| b.is_ascii_whitespace())
+        .count();
+    if leading + trailing >= segment.len() || !segment.contains(&b':') {
+        return;
+    }
+    candidates.push(Span::new(
+        Kind::Pair,
+        (y, start + leading),
+        (y, end - trailing - 1),
+    ));
+}
+
+/// The row around `span`, without its indentation.
+fn line(document: &Document, span: &Span, candidates: &mut Vec<Span>) {
+    let (y, _) = span.start;
+    if !is_blank(document, y) {
+        candidates.push(to_end_of_row(
+            Kind::Line,
+            (y, indent(document, y)),
+            document,
+            y,
+        ));
+    }
+}
+
+/// Every pair of brackets around `span`, with and without the brackets.
+fn brackets(document: &Document, span: &Span, candidates: &mut Vec<Span>) {
+    let mut from = span.start;
+    while let Some(open) = find_open(document, from) {
+        let Some(close) = find_close(document, open) else {
+            return;
+        };
+        if close < span.end {
+            return;
+        }
+        if let Some(inner) = trim(document, next(document, open), previous(document, close)) {
+            candidates.push(Span::new(Kind::Brackets, inner.0, inner.1));
+        }
+        candidates.push(Span::new(Kind::Brackets, open, close));
+        from = open;
+    }
+}
+
+/// The position after `(y, x)`, moving to the next row at the end of a row.
+fn next(document: &Document, (y, x): (usize, usize)) -> (usize, usize) {
+    if x + 1 < text_len(document, y) || !document.has_row(y + 1) {
+        (y, x + 1)
+    } else {
+        (y + 1, 0)
+    }
+}
+
+/// The position before `(y, x)`, moving to the previous row at the start of a row.
+fn previous(document: &Document, (y, x): (usize, usize)) -> (usize, usize) {
+    if x > 0 || y == 0 {
+        (y, x.saturating_sub(1))
+    } else {
+        (y - 1, text_len(document, y - 1).saturating_sub(1))
+    }
+}
+
+/// `start..=end` without whitespace at either side, if anything is left.
+fn trim(
+    document: &Document,
+    mut start: (usize, usize),
+    mut end: (usize, usize),
+) -> Option<((usize, usize), (usize, usize))> {
+    let is_space = |(y, x): (usize, usize)| {
+        bytes(document, y)
+            .get(x)
+            .is_none_or(|b| b.is_ascii_whitespace())
+    };
+    while start <= end && is_space(start) {
+        if start.0 < end.0 && start.1 + 1 >= text_len(document, start.0) {
+            start = (start.0 + 1, 0);
+        } else {
+            start.1 += 1;
+        }
+    }
+    while start <= end && is_space(end) {
+        if end.1 == 0 {
+            if end.0 == 0 {
+                return None;
+            }
+            end = (end.0 - 1, text_len(document, end.0 - 1).saturating_sub(1));
+        } else {
+            end.1 -= 1;
+        }
+    }
+    (start <= end).then_some((start, end))
+}
+
+/// The nearest unmatched opening bracket before `(y, x)`.
+fn find_open(document: &Document, (y, x): (usize, usize)) -> Option<(usize, usize)> {
+    let mut closed = Vec::new();
+    for row in (0..=y).rev() {
+        let bytes = bytes(document, row);
+        let end = if row == y {
+            x.min(bytes.len())
+        } else {
+            bytes.len()
+        };
+        for col in (0..end).rev() {
+            match bytes[col] {
+                b @ (b')' | b']' | b'}') => closed.push(b),
+                b @ (b'(' | b'[' | b'{') => match closed.pop() {
+                    Some(close) if close == closing(b) => {}
+                    _ => return Some((row, col)),
+                },
+                _ => {}
+            }
+        }
+    }
+    None
+}
+
+/// The bracket closing the one at `open`.
+fn find_close(document: &Document, open: (usize, usize)) -> Option<(usize, usize)> {
+    let open_byte = bytes(document, open.0)[open.1];
+    let close_byte = closing(open_byte);
+    let mut depth = 0;
+    for row in (open.0..).take_while(|&row| document.has_row(row)) {
+        let bytes = bytes(document, row);
+        let start = if row == open.0 { open.1 + 1 } else { 0 };
+        for (col, &b) in bytes.iter().enumerate().skip(start) {
+            if b == open_byte {
+                depth += 1;
+            } else if b == close_byte {
+                if depth == 0 {
+                    return Some((row, col));
+                }
+                depth -= 1;
+            }
+        }
+    }
+    None
+}
+
+fn closing(open: u8) -> u8 {
+    match open {
+        b'(' => b')',
+        b'[' => b']',
+        _ => b'}',
+    }
+}
+
+/// Whether row `y` belongs to the block started by row `head`: it is indented
+/// further, is a yaml sequence item under a key, or closes the head's brackets.
+fn is_child(document: &Document, head: usize, y: usize) -> bool {
+    let (head_indent, row_indent) = (indent(document, head), indent(document, y));
+    let closes = matches!(bytes(document, y).get(row_indent), Some(b')' | b']' | b'}'));
+    row_indent > head_indent
+        || (row_indent == head_indent
+            && (closes || (!is_item(document, head) && is_item(document, y))))
+}
+
+/// The indentation blocks of the row `span` starts on and of all its parents.
+fn subtrees(document: &Document, span: &Span, candidates: &mut Vec<Span>) {
+    let mut head = span.start.0;
+    if is_blank(document, head) {
+        return;
+    }
+    loop {
+        let mut end = head;
+        for y in (head + 1..).take_while(|&y| document.has_row(y)) {
+            if is_blank(document, y) {
+                continue;
+            }
+            if !is_child(document, head, y) {
+                break;
+            }
+            end = y;
+        }
+        if end > head {
+            candidates.push(to_end_of_row(
+                Kind::Subtree,
+                (head, indent(document, head)),
+                document,
+                end,
+            ));
+        }
+        let parent = (0..head)
+            .rev()
+            .filter(|&y| !is_blank(document, y))
+            .find(|&y| is_child(document, y, head));
+        match parent {
+            Some(parent) => head = parent,
+            None => return,
+        }
+    }
+}
+
+/// The rows around `span` up to the nearest blank rows.
+fn paragraph(document: &Document, span: &Span, candidates: &mut Vec<Span>) {
+    let mut start = span.start.0;
+    while start > 0 && !is_blank(document, start - 1) {
+        start -= 1;
+    }
+    let mut end = span.end.0;
+    while document.has_row(end + 1) && !is_blank(document, end + 1) {
+        end += 1;
+    }
+    if let Some((start, end)) = trim(document, (start, 0), (end, text_len(document, end))) {
+        candidates.push(Span::new(Kind::Paragraph, start, end));
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/test_structure.rs"]
+mod tests;
@@ -1,11 +1,5 @@
-use std::fs;
-use std::io::Write;
-
 use crate::Position;
-use termion::event::Key;
-use termion::input::TermRead;
-use termion::raw::{IntoRawMode, RawTerminal};
-use termion::{color, get_tty};
+use crate::backend::{Backend, Key};
 
 pub struct Size {
     pub width: u16,
@@ -13,134 +7,84 @@ pub struct Size {
 }
 pub struct Terminal {
     size: Size,
-    _stdout: Option<RawTerminal<std::io::Stdout>>,
-    tty: Option<fs::File>,
+    backend: Box<dyn Backend>,
+    /// The screen lines as last drawn, `None` for those that aren't known.
+    screen: Vec<Option<String>>,
 }
 
-impl Terminal {
-    pub(crate) fn new(maybe_size: Option<(u16, u16)>) -> Result<Self, std::io::Error> {
-        let size = match maybe_size {
-            None => termion::terminal_size()?,
-            Some(tup) => tup,
-        };
-
-        let tty = match maybe_size {
-            None => {
-                let tty = get_tty()?;
-                // Reset the cursor to the top corner of the screen
-                write!(&tty, "{}", termion::cursor::Goto(1, 1))?;
-                Some(tty)
-            }
-            Some(_) => None,
-        };
+impl Drop for Terminal {
+    // Raw mode is undone by the backend, but the cursor stays hidden
+    // unless shown again, even when the editor bails out early.
+    fn drop(&mut self) {
+        let _ = self.cursor_show();
+        let _ = self.flush();
+    }
+}
 
+impl Terminal {
+    pub(crate) fn new(backend: impl Backend + 'static) -> Result<Self, std::io::Error> {
+        let (width, height) = backend.size()?;
         Ok(Self {
             size: Size {
-                width: size.0,
-                height: size.1.saturating_sub(2),
+                width,
+                height: height.saturating_sub(2),
             },
-            _stdout: if maybe_size.is_none() {
-                Some(std::io::stdout().into_raw_mode()?)
-            } else {
-                None
-            },
-            tty,
+            backend: Box::new(backend),
+            screen: Vec::new(),
         })
     }
     pub(crate) fn size(&self) -> &Size {
         &self.size
     }
     pub(crate) fn clear_screen(&mut self) -> std::io::Result<()> {
-        if let Some(tty) = self.tty.as_mut() {
-            write!(tty, "{}", termion::clear::All)?;
-        }
-        Ok(())
+        self.screen.clear();
+        self.backend.clear_screen()
     }
 
+    /// Writes out what was drawn since the last flush, in one go.
     pub(crate) fn flush(&mut self) -> Result<(), std::io::Error> {
-        if self.tty.is_some() {
-            std::io::stdout().flush()?;
+        self.backend.flush()
+    }
+
+    /// Draws `line` on screen line `y`, unless it is there already.
+    pub(crate) fn draw_line(&mut self, y: usize, line: &str) -> std::io::Result<()> {
+        if self.screen.len() <= y {
+            self.screen.resize(y + 1, None);
         }
+        if self.screen[y].as_deref() == Some(line) {
+            return Ok(());
+        }
+        self.backend.goto(0, u16::try_from(y).unwrap_or(u16::MAX))?;
+        self.backend.clear_line()?;
+        self.backend.print(line)?;
+        self.screen[y] = Some(line.to_string());
         Ok(())
     }
     pub(crate) fn read_key(&mut self) -> Result<Key, std::io::Error> {
-        if let Some(tty) = self.tty.as_mut() {
-            loop {
-                if let Some(key) = tty.try_clone()?.keys().next() {
-                    return key;
-                }
-            }
-        }
-        // This only happens in test
-        Ok(Key::Esc)
+        self.backend.read_key()
     }
+    /// Writes `s` wherever the cursor is, after which the lines drawn are
+    /// no longer known.
     pub(crate) fn writeln(&mut self, s: &str) -> std::io::Result<()> {
-        if let Some(tty) = self.tty.as_mut() {
-            writeln!(tty, "{s}\r")?;
-        }
-        Ok(())
-    }
-    pub(crate) fn write(&mut self, s: &str) -> std::io::Result<()> {
-        if let Some(tty) = self.tty.as_mut() {
-            write!(tty, "{s}")?;
-        }
-        Ok(())
+        self.screen.clear();
+        self.backend.print(s)?;
+        self.backend.print("\r\n")
     }
 
     pub(crate) fn cursor_position(&mut self, position: &Position) -> std::io::Result<()> {
-        if let Some(tty) = self.tty.as_mut() {
-            let &Position { mut x, mut y, .. } = position;
-            x = x.saturating_add(1);
-            y = y.saturating_add(1);
-            let x = x as u16;
-            let y = y as u16;
-            write!(tty, "{}", termion::cursor::Goto(x, y))?;
-        }
-        Ok(())
+        let &Position { x, y, .. } = position;
+        self.backend.goto(x as u16, y as u16)
     }
 
     pub(crate) fn cursor_hide(&mut self) -> std::io::Result<()> {
-        if let Some(tty) = self.tty.as_mut() {
-            write!(tty, "{}", termion::cursor::Hide)?;
-        }
-        Ok(())
+        self.backend.show_cursor(false)
     }
 
     pub(crate) fn cursor_show(&mut self) -> std::io::Result<()> {
-        if let Some(tty) = self.tty.as_mut() {
-            write!(tty, "{}", termion::cursor::Show)?;
-        }
-        Ok(())
-    }
-
-    pub(crate) fn clear_current_line(&mut self) -> std::io::Result<()> {
-        if let Some(tty) = self.tty.as_mut() {
-            write!(tty, "{}", termion::clear::CurrentLine)?;
-        }
-        Ok(())
-    }
-    pub(crate) fn set_bg_color(&mut self, color: color::Rgb) -> std::io::Result<()> {
-        if let Some(tty) = self.tty.as_mut() {
-            write!(tty, "{}", color::Bg(color))?;
-        }
-        Ok(())
-    }
-    pub(crate) fn reset_bg_color(&mut self) -> std::io::Result<()> {
-        if let Some(tty) = self.tty.as_mut() {
-            write!(tty, "{}", color::Bg(color::Reset))?;
-        }
-        Ok(())
-    }
-    pub(crate) fn set_fg_color(&mut self, color: color::Rgb) -> std::io::Result<()> {
-        if let Some(tty) = self.tty.as_mut() {
-            write!(tty, "{}", color::Fg(color))?;
-        }
-        Ok(())
-    }
-    pub(crate) fn reset_fg_color(&mut self) -> std::io::Result<()> {
-        if let Some(tty) = self.tty.as_mut() {
-            write!(tty, "{}", color::Fg(color::Reset))?;
-        }
-        Ok(())
+        self.backend.show_cursor(true)
     }
 }
+
+#[cfg(test)]
+#[path = "tests/test_terminal.rs"]
+mod tests;
@@ -0,0 +1,172 @@
+use super::*;
+
+fn parse(args: &[&str]) -> Result<Args, String> {
+    Args::parse(args.iter().map(|arg| arg.to_string()))
+}
+
+#[test]
+fn test_source() {
+    assert_eq!(parse(&[]).unwrap().source, Source::Stdin);
+    assert_eq!(
+        parse(&["pods.txt"]).unwrap().source,
+        Source::Files(vec!["pods.txt".to_string()])
+    );
+    assert_eq!(
+        parse(&["a.log", "-", "c.yaml"]).unwrap().source,
+        Source::Files(vec![
+            "a.log".to_string(),
+            "-".to_string(),
+            "c.yaml".to_string()
+        ])
+    );
+    assert_eq!(parse(&["--tmux"]).unwrap().source, Source::Tmux(None));
+    assert_eq!(
+        parse(&["--tmux=%3"]).unwrap().source,
+        Source::Tmux(Some("%3".to_string()))
+    );
+    assert_eq!(parse(&["--history"]).unwrap().source, Source::History);
+    assert_eq!(parse(&["-"]).unwrap().source, Source::Stdin);
+}
+
+#[test]
+fn test_tokenizer() {
+    assert_eq!(parse(&[]).unwrap().tokenizer, None);
+    assert_eq!(
+        parse(&["--tokenizer", "line"]).unwrap().tokenizer,
+        Some(Tokenizer::Line)
+    );
+    assert_eq!(
+        parse(&["--delimiter=,"]).unwrap().tokenizer,
+        Some(Tokenizer::String(",".to_string()))
+    );
+    assert_eq!(
+        parse(&["--regex", "[a-z]+"]).unwrap().tokenizer,
+        Some(Tokenizer::Regex(Regex::new("[a-z]+").unwrap()))
+    );
+    assert_eq!(
+        parse(&["--tokenizer=words"]),
+        Err("Unknown tokenizer: words".to_string())
+    );
+    assert_eq!(
+        parse(&["--delimiter="]),
+        Err("The delimiter can't be empty".to_string())
+    );
+    assert_eq!(
+        parse(&["--regex", "[a-z"]),
+        Err("Invalid regex: [a-z".to_string())
+    );
+}
+
+#[test]
+fn test_start_and_output() {
+    let args = parse(&[
+        "--start-line=3",
+        "--start-search",
+        "Running",
+        "--output",
+        "-",
+    ])
+    .unwrap();
+    assert_eq!(args.start_line, Some(3));
+    assert_eq!(args.start_search, Some("Running".to_string()));
+    assert_eq!(args.output_file, Some("-".to_string()));
+    assert_eq!(
+        parse(&["--start-line=0"]),
+        Err("Invalid start line: 0".to_string())
+    );
+    assert_eq!(parse(&[]).unwrap().clipboard, Backend::Auto);
+    assert_eq!(
+        parse(&["--clipboard-backend=tmux"]).unwrap().clipboard,
+        Backend::Tmux
+    );
+    assert_eq!(parse(&["--no-clipboard"]).unwrap().clipboard, Backend::None);
+    assert_eq!(
+        parse(&["--clipboard-backend=x11"]),
+        Err("Unknown clipboard backend: x11".to_string())
+    );
+    assert_eq!(parse(&["--tab-width=4"]).unwrap().tab_width, Some(4));
+    assert_eq!(
+        parse(&["--tab-width", "0"]),
+        Err("Invalid tab width: 0".to_string())
+    );
+    assert_eq!(parse(&[]).unwrap().encoding, None);
+    assert_eq!(
+        parse(&["--encoding", "latin1"]).unwrap().encoding,
+        Some(Encoding::Latin1)
+    );
+    assert_eq!(
+        parse(&["--encoding=windows-1252"]).unwrap().encoding,
+        Some(Encoding::Windows1252)
+    );
+    assert_eq!(parse(&["--encoding=auto"]).unwrap().encoding, None);
+    assert_eq!(
+        parse(&["--encoding=ebcdic"]),
+        Err("Unknown encoding: ebcdic".to_string())
+    );
+    assert!(parse(&["-h"]).unwrap().help);
+    assert!(parse(&["--version"]).unwrap().version);
+}
+
+#[test]
+fn test_values() {
+    let args = parse(&["--preset", "k8s", "--join=, ", "--format", "'{}'", "--trim"]).unwrap();
+    assert_eq!(args.preset, Some("k8s".to_string()));
+    assert_eq!(
+        args.output,
+        OutputFormat {
+            separator: Some(", ".to_string()),
+            trim: true,
+            template: Some("'{}'".to_string()),
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn test_display() {
+    assert_eq!(parse(&[]).unwrap().line_numbers, LineNumbers::Off);
+    assert_eq!(
+        parse(&["--line-numbers"]).unwrap().line_numbers,
+        LineNumbers::Absolute
+    );
+    assert_eq!(
+        parse(&["--line-numbers=relative"]).unwrap().line_numbers,
+        LineNumbers::Relative
+    );
+    assert!(parse(&["--wrap"]).unwrap().wrap);
+    assert_eq!(parse(&[]).unwrap().header, None);
+    assert!(!parse(&[]).unwrap().allow_empty);
+    assert!(parse(&["--allow-empty"]).unwrap().allow_empty);
+    assert_eq!(parse(&["--header"]).unwrap().header, Some(1));
+    assert_eq!(parse(&["--header=2"]).unwrap().header, Some(2));
+    assert_eq!(parse(&["--no-header"]).unwrap().header, Some(0));
+    assert_eq!(
+        parse(&["--header=x"]),
+        Err("Invalid number of header rows: x".to_string())
+    );
+    assert_eq!(
+        parse(&["--line-numbers=sideways"]),
+        Err("Unknown line numbers: sideways".to_string())
+    );
+}
+
+#[test]
+fn test_errors() {
+    assert_eq!(
+        parse(&["--preset"]),
+        Err("--preset needs a value".to_string())
+    );
+    assert_eq!(
+        parse(&["--nope"]),
+        Err("Unknown option: --nope".to_string())
+    );
+    assert_eq!(parse(&["-x"]), Err("Unknown option: -x".to_string()));
+    for args in [
+        &["--tmux", "pods.txt"][..],
+        &["-", "--history"],
+        &["--history", "--tmux=%3"],
+        &["--tmux", "--history"],
+    ] {
+        assert_eq!(parse(args), Err(SOURCES.to_string()));
+    }
+}
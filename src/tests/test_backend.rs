@@ -0,0 +1,63 @@
+use super::*;
+
+#[test]
+fn test_memory_keys() {
+    let mut memory = Memory::new(10, 5).with_keys([Key::Down, Key::Char('q')]);
+    assert_eq!(memory.read_key().unwrap(), Key::Down);
+    assert_eq!(memory.read_key().unwrap(), Key::Char('q'));
+    assert_eq!(memory.read_key().unwrap(), Key::Esc);
+}
+
+#[test]
+fn test_memory_output_on_flush() {
+    let mut memory = Memory::new(10, 5);
+    let output = memory.output();
+    memory.goto(2, 1).unwrap();
+    memory.print("ab").unwrap();
+    assert_eq!(*output.borrow(), "");
+    memory.flush().unwrap();
+    assert_eq!(*output.borrow(), "\x1b[2;3Hab");
+}
+
+#[cfg(all(feature = "termion", not(feature = "crossterm")))]
+#[test]
+fn test_termion_keys() {
+    use termion::event::Key as TermionKey;
+    assert_eq!(termion_key(TermionKey::Char('\n')), Some(Key::Char('\n')));
+    assert_eq!(termion_key(TermionKey::Ctrl('c')), Some(Key::Ctrl('c')));
+    assert_eq!(termion_key(TermionKey::BackTab), Some(Key::BackTab));
+    assert_eq!(termion_key(TermionKey::PageUp), Some(Key::PageUp));
+    assert_eq!(termion_key(TermionKey::Null), None);
+}
+
+#[cfg(feature = "crossterm")]
+#[test]
+fn test_crossterm_keys() {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    let key = |code, modifiers| crossterm_key(KeyEvent::new(code, modifiers));
+    let none = KeyModifiers::NONE;
+    assert_eq!(key(KeyCode::Char('a'), none), Some(Key::Char('a')));
+    assert_eq!(
+        key(KeyCode::Char('A'), KeyModifiers::SHIFT),
+        Some(Key::Char('A'))
+    );
+    assert_eq!(
+        key(KeyCode::Char('c'), KeyModifiers::CONTROL),
+        Some(Key::Ctrl('c'))
+    );
+    assert_eq!(
+        key(KeyCode::Char('x'), KeyModifiers::ALT),
+        Some(Key::Alt('x'))
+    );
+    // The editor takes ENTER and TAB as the characters termion reads them as
+    assert_eq!(key(KeyCode::Enter, none), Some(Key::Char('\n')));
+    assert_eq!(key(KeyCode::Tab, none), Some(Key::Char('\t')));
+    assert_eq!(
+        key(KeyCode::BackTab, KeyModifiers::SHIFT),
+        Some(Key::BackTab)
+    );
+    assert_eq!(key(KeyCode::PageDown, none), Some(Key::PageDown));
+    assert_eq!(key(KeyCode::F(5), none), Some(Key::F(5)));
+    assert_eq!(key(KeyCode::Esc, none), Some(Key::Esc));
+    assert_eq!(key(KeyCode::CapsLock, none), None);
+}
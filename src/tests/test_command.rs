@@ -0,0 +1,31 @@
+use super::*;
+
+#[test]
+fn test_shell_quote() {
+    assert_eq!(
+        shell_quote("nginx-7c5ddbdf54-2xq9z"),
+        "nginx-7c5ddbdf54-2xq9z"
+    );
+    assert_eq!(shell_quote("a b"), "'a b'");
+    assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    assert_eq!(shell_quote("$(rm -rf /)"), "'$(rm -rf /)'");
+    assert_eq!(shell_quote(""), "''");
+}
+
+#[test]
+fn test_command_line() {
+    let command = CommandTemplate {
+        key: 'l',
+        action: Action::Exec,
+        template: "kubectl logs {} --namespace {}".to_string(),
+    };
+    assert_eq!(
+        command.command_line("a b"),
+        "kubectl logs 'a b' --namespace 'a b'"
+    );
+}
+
+#[test]
+fn test_output() {
+    assert_eq!(output("echo out; echo err >&2").unwrap(), b"out\nerr\n");
+}
@@ -0,0 +1,74 @@
+use std::io::{Read, Write};
+
+use super::*;
+
+const TEXT: &str = "NAME      READY   STATUS\nweb-0     1/1     Running\n";
+
+fn read(compressed: &[u8]) -> String {
+    let mut text = String::new();
+    decompress(compressed)
+        .unwrap()
+        .read_to_string(&mut text)
+        .unwrap();
+    text
+}
+
+#[test]
+fn test_detect() {
+    assert_eq!(
+        Compression::detect(&[0x1f, 0x8b, 8]),
+        Some(Compression::Gzip)
+    );
+    assert_eq!(Compression::detect(b"BZh91AY"), Some(Compression::Bzip2));
+    assert_eq!(Compression::detect(b"NAME"), None);
+    assert_eq!(Compression::detect(b""), None);
+}
+
+#[test]
+fn test_plain() {
+    assert_eq!(read(TEXT.as_bytes()), TEXT);
+}
+
+#[test]
+fn test_gzip() {
+    let mut compressed = Vec::new();
+    // Two members, like rotated logs that were concatenated.
+    for half in [&TEXT[..10], &TEXT[10..]] {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(half.as_bytes()).unwrap();
+        compressed.extend(encoder.finish().unwrap());
+    }
+    assert_eq!(read(&compressed), TEXT);
+}
+
+#[test]
+fn test_zstd() {
+    let compressed = zstd::encode_all(TEXT.as_bytes(), 0).unwrap();
+    assert_eq!(read(&compressed), TEXT);
+}
+
+#[test]
+fn test_bzip2() {
+    let mut compressed = Vec::new();
+    bzip2::read::BzEncoder::new(TEXT.as_bytes(), bzip2::Compression::default())
+        .read_to_end(&mut compressed)
+        .unwrap();
+    assert_eq!(read(&compressed), TEXT);
+}
+
+#[test]
+fn test_xz() {
+    let mut compressed = Vec::new();
+    liblzma::read::XzEncoder::new(TEXT.as_bytes(), 6)
+        .read_to_end(&mut compressed)
+        .unwrap();
+    assert_eq!(read(&compressed), TEXT);
+}
+
+#[test]
+fn test_corrupt() {
+    let mut text = String::new();
+    let result =
+        decompress(&[0x1f, 0x8b, 0, 0][..]).and_then(|mut input| input.read_to_string(&mut text));
+    assert!(result.is_err());
+}
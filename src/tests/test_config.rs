@@ -0,0 +1,80 @@
+use super::*;
+
+const CONFIG: &str = "
+# Global commands
+y = view kubectl get -o yaml {}
+d = view cat {}
+
+[k8s]
+l = exec kubectl logs -f {}
+d = view kubectl describe pod {}
+
+[empty]
+";
+
+fn command(key: char, action: Action, template: &str) -> CommandTemplate {
+    CommandTemplate {
+        key,
+        action,
+        template: template.to_string(),
+    }
+}
+
+#[test]
+fn test_global_preset() {
+    let config = Config::parse(CONFIG).unwrap();
+    assert_eq!(
+        config.preset(None).unwrap().commands,
+        vec![
+            command('y', Action::View, "kubectl get -o yaml {}"),
+            command('d', Action::View, "cat {}"),
+        ]
+    );
+    assert_eq!(config.preset(None), config.preset(Some("empty")));
+}
+
+#[test]
+fn test_named_preset_overrides() {
+    let config = Config::parse(CONFIG).unwrap();
+    assert_eq!(
+        config.preset(Some("k8s")).unwrap().commands,
+        vec![
+            command('y', Action::View, "kubectl get -o yaml {}"),
+            command('l', Action::Exec, "kubectl logs -f {}"),
+            command('d', Action::View, "kubectl describe pod {}"),
+        ]
+    );
+}
+
+#[test]
+fn test_unknown_preset() {
+    let config = Config::parse(CONFIG).unwrap();
+    assert_eq!(
+        config.preset(Some("nope")),
+        Err("Unknown preset: nope".to_string())
+    );
+}
+
+#[test]
+fn test_errors() {
+    assert_eq!(
+        Config::parse("[k8s"),
+        Err("line 1: expected ']'".to_string())
+    );
+    assert_eq!(
+        Config::parse("\nl exec ls"),
+        Err("line 2: expected 'key = value'".to_string())
+    );
+    assert_eq!(
+        Config::parse("ll = exec ls"),
+        Err("line 1: command keys must be a single character, got 'll'".to_string())
+    );
+    assert_eq!(
+        Config::parse("l = run ls"),
+        Err("line 1: expected 'exec' or 'view', got 'run'".to_string())
+    );
+    assert_eq!(
+        Config::parse("l = exec"),
+        Err("line 1: missing command".to_string())
+    );
+}
@@ -65,7 +65,102 @@ fn test_find_an_again() {
         Some(Position {
             x: 10,
             y: 13,
-            longest_row: doc.longest_row
+            longest_row: doc.longest_row()
         })
     );
 }
+
+#[test]
+fn test_find_goes_round() {
+    let doc = Document::new("ab\ncd\nab\n".as_bytes()).unwrap();
+    let at = |x, y| Position {
+        x,
+        y,
+        longest_row: doc.longest_row(),
+    };
+    assert_eq!(
+        doc.find("ab", &at(1, 2), SearchDirection::Forward),
+        Some(at(0, 0))
+    );
+    assert_eq!(
+        doc.find("ab", &at(0, 0), SearchDirection::Backward),
+        Some(at(0, 2))
+    );
+    assert_eq!(
+        doc.find("b", &at(2, 0), SearchDirection::Forward),
+        Some(at(1, 2))
+    );
+    assert_eq!(doc.find("x", &at(0, 1), SearchDirection::Forward), None);
+}
+
+#[test]
+fn test_find_cost_does_not_grow_with_the_stride() {
+    let text: String = (0..8 * 1024).map(|i| format!("line {i}\n")).collect();
+    let doc = Document::new(text.as_bytes()).unwrap();
+    let at = |y| Position {
+        x: 0,
+        y,
+        longest_row: doc.longest_row(),
+    };
+    for direction in [SearchDirection::Forward, SearchDirection::Backward] {
+        doc.lines.scanned.set(0);
+        assert_eq!(doc.find("missing", &at(100), direction), None);
+        // Each line is looked through about once, not once per line before it
+        assert!(doc.lines.scanned.get() <= 3 * text.len());
+    }
+    assert_eq!(
+        doc.find("line 8000", &at(100), SearchDirection::Forward),
+        Some(at(8000))
+    );
+}
+
+#[test]
+fn test_is_blank() {
+    assert!(Document::new("".as_bytes()).unwrap().is_blank());
+    assert!(Document::new(" \n\t\n\n".as_bytes()).unwrap().is_blank());
+    assert!(!Document::new("\n x\n".as_bytes()).unwrap().is_blank());
+}
+
+#[test]
+fn test_set_encoding() {
+    let mut doc = Document::new(b"caf\xe9\n".as_slice()).unwrap();
+    assert_eq!(doc.row(0).unwrap().text(), "caf\u{fffd}");
+    doc.set_encoding(Encoding::Latin1);
+    assert_eq!(doc.row(0).unwrap().text(), "café");
+
+    let text: Vec<u8> = "\u{feff}a\r\nb\r\n"
+        .encode_utf16()
+        .flat_map(u16::to_le_bytes)
+        .collect();
+    let mut doc = Document::new(text.as_slice()).unwrap();
+    doc.set_encoding(Encoding::Utf16Le);
+    assert_eq!(doc.len(), 2);
+    assert_eq!(doc.row(1).unwrap().text(), "b");
+}
+
+#[test]
+fn test_overstruck_text() {
+    let doc = Document::new("N\u{8}NAME\n10%\r100%\n".as_bytes()).unwrap();
+    assert_eq!(doc.text(0), "NAME");
+    assert_eq!(doc.row(0).unwrap().text(), "NAME");
+    assert_eq!(doc.text(1), "100%");
+}
+
+#[test]
+fn test_row_out_of_range() {
+    let doc = Document::new("".as_bytes()).unwrap();
+    assert_eq!(doc.len(), 1);
+    assert_eq!(doc.row(0).unwrap().text(), "");
+    assert!(doc.row(3).is_none());
+
+    let doc = Document::new("a\nb".as_bytes()).unwrap();
+    assert_eq!(doc.row(1).unwrap().text(), "b");
+    assert!(doc.row(5).is_none());
+    assert_eq!(doc.clamp(5), 1);
+    let past_end = HighlightedText::new_token(Position {
+        x: 0,
+        y: 5,
+        longest_row: 1,
+    });
+    assert_eq!(doc.get_text(&past_end), "");
+}
@@ -1,38 +1,54 @@
-use std::io::BufReader;
-
 use super::*;
+use crate::backend::Memory;
 
+#[derive(Clone, Copy)]
 enum TestFile {
     GetPods,
+    GetPodsAll,
     GetNs,
     PodYaml,
     Csv,
     GitStatus,
     VisualGitStatus,
+    UnicodeGitStatus,
 }
 
 impl TestFile {
     fn to_str(self) -> &'static str {
         match self {
             TestFile::GetPods => include_str!("files/k-get-po.txt"),
+            TestFile::GetPodsAll => include_str!("files/k-get-po-a.txt"),
             TestFile::GetNs => include_str!("files/k-ns.txt"),
             TestFile::PodYaml => include_str!("files/pod.yaml"),
             TestFile::Csv => include_str!("files/sample-vocabulary.csv"),
             TestFile::GitStatus => include_str!("files/git-status.txt"),
             TestFile::VisualGitStatus => include_str!("files/visual-git-status.txt"),
+            TestFile::UnicodeGitStatus => include_str!("files/unicode-git-status.txt"),
         }
     }
 }
 
 fn test_editor(test_file: TestFile) -> Editor {
-    let buf = BufReader::new(stringreader::StringReader::new(test_file.to_str()));
-    let document = Document::new(buf).unwrap();
-    let terminal = Terminal::new(Some((150, 150))).unwrap();
-    Editor::new(document, None, terminal).unwrap()
+    editor_with(test_file.to_str(), 150, 150)
+}
+
+/// An editor showing `text` on a screen of `width` by `height`.
+fn editor_with(text: &str, width: u16, height: u16) -> Editor {
+    editor_on(text, Memory::new(width, height))
+}
+
+/// An editor showing `text` on `memory`, to read what is drawn from it.
+fn editor_on(text: &str, memory: Memory) -> Editor {
+    let document = Document::new(text.as_bytes()).unwrap();
+    let terminal = Terminal::new(memory).unwrap();
+    Editor::new(document, vec![], terminal).unwrap()
 }
 
 fn test_key_seq(test_file: TestFile, keys: Vec<Key>, expected: &'static str) {
-    let mut editor = test_editor(test_file);
+    test_keys(test_editor(test_file), keys, expected);
+}
+
+fn test_keys(mut editor: Editor, keys: Vec<Key>, expected: &'static str) {
     assert_eq!(editor.should_quit, ShouldQuit::No);
     for key in keys {
         editor.process_keypress(key).unwrap();
@@ -114,6 +130,31 @@ mod normal {
         test_key_seq(TestFile::PodYaml, vec![Key::Down; 9], "creationTimestamp:");
     }
 
+    #[test]
+    fn test_jump_to_bottom() {
+        let mut editor = test_editor(TestFile::GitStatus);
+        editor.jump_to_bottom();
+        editor.process_keypress(Key::Char('\n')).unwrap();
+        assert_eq!(
+            editor.should_quit,
+            ShouldQuit::Ye(CopyStatus::Success("track)".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_jump_to_line() {
+        let mut editor = test_editor(TestFile::GetPods);
+        editor.jump_to_line(4);
+        test_keys(editor, vec![], "logdb-shared-query-7dbd46c867-8ktf9");
+        let mut editor = test_editor(TestFile::GetPods);
+        editor.jump_to_line(usize::MAX);
+        test_keys(
+            editor,
+            vec![Key::Down],
+            "logdb-shared-ingest-756cfb4c58-68pgk",
+        );
+    }
+
     #[test]
     fn test_empty_token_rows() {
         test_key_seq(
@@ -207,6 +248,13 @@ mod search {
         assert_eq!(editor.should_quit, ShouldQuit::Ye(CopyStatus::Noop));
     }
 
+    #[test]
+    fn test_start_search() {
+        let mut editor = test_editor(TestFile::PodYaml);
+        editor.start_search("kind");
+        test_keys(editor, vec![], "kind");
+    }
+
     #[test]
     fn test_search_and_normal() {
         test_key_seq(
@@ -277,7 +325,7 @@ mod visual {
                 Key::Down,
                 Key::Down,
             ],
-            "co\nco\nco\nco\n  \not",
+            "co\nco\nco\nco\n\not",
         );
     }
 
@@ -302,3 +350,965 @@ mod visual {
         );
     }
 }
+
+mod stream {
+    use super::*;
+
+    #[test]
+    fn test_stream_two_rows() {
+        let mut keys = vec![Key::Char('v')];
+        keys.extend(vec![Key::Right; 6]);
+        keys.extend([Key::Char('s'), Key::Down]);
+        test_key_seq(TestFile::PodYaml, keys, "sion: v1\nkind: P");
+    }
+
+    #[test]
+    fn test_stream_backwards() {
+        let mut keys = vec![Key::Down, Key::Down, Key::Char('v')];
+        keys.extend(vec![Key::Right; 4]);
+        keys.extend([Key::Char('s'), Key::Up, Key::Up]);
+        test_key_seq(TestFile::PodYaml, keys, "ersion: v1\nkind: Pod\nmetad");
+    }
+
+    #[test]
+    fn test_block_to_stream() {
+        let mut keys = vec![Key::Char('v'), Key::Char('v'), Key::Down];
+        keys.extend(vec![Key::Right; 3]);
+        keys.push(Key::Char('s'));
+        test_key_seq(TestFile::PodYaml, keys, "apiVersion: v1\nkind");
+    }
+
+    #[test]
+    fn test_stream_past_end_of_rows() {
+        let mut keys = vec![Key::Char('v'), Key::Char('s'), Key::Down, Key::Char('$')];
+        keys.push(Key::Down);
+        test_key_seq(
+            TestFile::PodYaml,
+            keys,
+            "apiVersion: v1\nkind: Pod\nmetadata:",
+        );
+    }
+}
+
+mod line {
+    use super::*;
+
+    #[test]
+    fn test_current_line() {
+        test_key_seq(
+            TestFile::PodYaml,
+            vec![Key::Down, Key::Down, Key::Char('V')],
+            "metadata:",
+        );
+    }
+
+    #[test]
+    fn test_yaml_block() {
+        test_key_seq(
+            TestFile::PodYaml,
+            vec![Key::Down, Key::Down, Key::Char('V'), Key::Down, Key::Down],
+            "metadata:\n  annotations:\n    cluster-autoscaler.kubernetes.io/safe-to-evict: \"false\"",
+        );
+    }
+
+    #[test]
+    fn test_git_status_lines_upwards() {
+        test_key_seq(
+            TestFile::VisualGitStatus,
+            vec![Key::Down; 5]
+                .into_iter()
+                .chain([Key::Char('V'), Key::Up, Key::Right])
+                .collect(),
+            "\tcodefresh/generated_yaml/meta/meta-uqlscaler.yaml\n\tcodefresh/generated_yaml/meta/uqlscaler.yaml",
+        );
+    }
+
+    #[test]
+    fn test_from_cursor_and_block() {
+        test_key_seq(
+            TestFile::PodYaml,
+            vec![Key::Char('v'), Key::Char('v'), Key::Down, Key::Char('V')],
+            "apiVersion: v1\nkind: Pod",
+        );
+    }
+}
+
+mod structure {
+    use super::*;
+
+    #[test]
+    fn test_expand_pair() {
+        test_key_seq(
+            TestFile::PodYaml,
+            vec![
+                Key::Down,
+                Key::Down,
+                Key::Down,
+                Key::Down,
+                Key::Right,
+                Key::Char('+'),
+            ],
+            "cluster-autoscaler.kubernetes.io/safe-to-evict: \"false\"",
+        );
+    }
+
+    #[test]
+    fn test_expand_subtree() {
+        test_key_seq(
+            TestFile::PodYaml,
+            vec![Key::Up, Key::Up, Key::Up, Key::Char('+'), Key::Char('+')],
+            "podIPs:\n  - ip: 10.12.25.98",
+        );
+    }
+
+    #[test]
+    fn test_shrink() {
+        test_key_seq(
+            TestFile::PodYaml,
+            vec![
+                Key::Down,
+                Key::Down,
+                Key::Down,
+                Key::Down,
+                Key::Right,
+                Key::Char('+'),
+                Key::Char('+'),
+                Key::Char('-'),
+                Key::Char('-'),
+            ],
+            "\"false\"",
+        );
+    }
+}
+
+mod tree {
+    use super::*;
+
+    fn pod_annotation() -> Vec<Key> {
+        vec![Key::Down, Key::Down, Key::Down, Key::Down, Key::Char('p')]
+    }
+
+    #[test]
+    fn test_copy_value() {
+        test_key_seq(TestFile::PodYaml, pod_annotation(), "false");
+    }
+
+    #[test]
+    fn test_copy_path() {
+        let mut editor = test_editor(TestFile::PodYaml);
+        for key in pod_annotation() {
+            editor.process_keypress(key).unwrap();
+        }
+        editor.process_keypress(Key::Left).unwrap();
+        editor.process_keypress(Key::Char('p')).unwrap();
+        assert_eq!(
+            editor.should_quit,
+            ShouldQuit::Ye(CopyStatus::Success(".metadata.annotations".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_copy_subtree() {
+        let mut keys = pod_annotation();
+        keys.extend([Key::Left, Key::Left, Key::Char('s')]);
+        let mut editor = test_editor(TestFile::PodYaml);
+        for key in keys {
+            editor.process_keypress(key).unwrap();
+        }
+        let ShouldQuit::Ye(CopyStatus::Success(text)) = &editor.should_quit else {
+            panic!("Expected a copy, got {:?}", editor.should_quit);
+        };
+        assert!(text.starts_with("annotations:\n  cluster-autoscaler"));
+        assert!(text.ends_with("creationTimestamp: \"2023-09-18T21:16:04Z\""));
+    }
+
+    #[test]
+    fn test_not_a_tree() {
+        let mut editor = test_editor(TestFile::GetPods);
+        editor.process_keypress(Key::Char('p')).unwrap();
+        assert_eq!(editor.input_mode, InputMode::Normal);
+        assert_eq!(editor.status_message, NO_TREE_STRING);
+    }
+
+    #[test]
+    fn test_empty_key() {
+        for text in [":", "a: 1\n: b"] {
+            let mut editor = editor_with(text, 150, 150);
+            editor.process_keypress(Key::Char('p')).unwrap();
+            editor.process_keypress(Key::Char('p')).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_too_large_for_a_tree() {
+        let yaml = "key: value\n".repeat(2 * 1024 * 1024);
+        let mut editor = editor_with(&yaml, 150, 150);
+        editor.process_keypress(Key::Char('p')).unwrap();
+        assert_eq!(editor.input_mode, InputMode::Normal);
+        assert_eq!(editor.status_message, LARGE_TREE_STRING);
+        assert!(!editor.document.rows_found().1);
+    }
+}
+
+mod gutter {
+    use super::*;
+
+    fn narrow_editor() -> Editor {
+        editor_with(TestFile::GetPods.to_str(), 20, 10)
+    }
+
+    fn plain(gutter: String) -> String {
+        gutter
+            .replace(&Fg(GUTTER_FG_COLOR).to_string(), "")
+            .replace(&Fg(Color::Reset).to_string(), "")
+    }
+
+    #[test]
+    fn test_numbers() {
+        let mut editor = narrow_editor();
+        assert_eq!(editor.gutter(0), "");
+        editor.process_keypress(Key::Char('#')).unwrap();
+        assert_eq!(plain(editor.gutter(0)), " 1 ");
+        assert_eq!(plain(editor.gutter(18)), "19 ");
+        editor.process_keypress(Key::Down).unwrap();
+        editor.process_keypress(Key::Down).unwrap();
+        editor.process_keypress(Key::Char('#')).unwrap();
+        assert_eq!(plain(editor.gutter(0)), " 2 ");
+        assert_eq!(plain(editor.gutter(2)), " 3 ");
+        assert_eq!(plain(editor.gutter(18)), "16 ");
+        editor.process_keypress(Key::Char('#')).unwrap();
+        assert_eq!(editor.gutter(0), "");
+    }
+
+    #[test]
+    fn test_scroll_within_text_width() {
+        let mut editor = narrow_editor();
+        editor.set_line_numbers(LineNumbers::Absolute);
+        editor.process_keypress(Key::Char('v')).unwrap();
+        for _ in 0..16 {
+            editor.process_keypress(Key::Right).unwrap();
+        }
+        assert_eq!(editor.offset.x, 0);
+        editor.process_keypress(Key::Right).unwrap();
+        assert_eq!(editor.offset.x, 1);
+    }
+}
+
+mod wrap {
+    use super::*;
+
+    fn wrapped_editor() -> Editor {
+        // 8 rows of text, with every row of pods taking 6 screen lines
+        let mut editor = editor_with(TestFile::GetPods.to_str(), 20, 10);
+        editor.set_wrap(true);
+        editor
+    }
+
+    #[test]
+    fn test_scroll_by_screen_line() {
+        let mut editor = wrapped_editor();
+        editor.process_keypress(Key::Char('v')).unwrap();
+        for _ in 0..45 {
+            editor.process_keypress(Key::Right).unwrap();
+        }
+        assert_eq!((editor.offset.y, editor.wrap_offset), (0, 0));
+        editor.process_keypress(Key::Down).unwrap();
+        assert_eq!(
+            (editor.offset.x, editor.offset.y, editor.wrap_offset),
+            (0, 0, 1)
+        );
+        editor.process_keypress(Key::Up).unwrap();
+        assert_eq!((editor.offset.y, editor.wrap_offset), (0, 1));
+        editor.process_keypress(Key::Home).unwrap();
+        assert_eq!((editor.offset.y, editor.wrap_offset), (0, 0));
+    }
+
+    #[test]
+    fn test_page_down() {
+        let mut editor = wrapped_editor();
+        editor.process_keypress(Key::PageDown).unwrap();
+        assert_eq!(editor.cursor_position.y, 1);
+        editor.process_keypress(Key::Char('W')).unwrap();
+        editor.process_keypress(Key::PageDown).unwrap();
+        assert_eq!(editor.cursor_position.y, 9);
+    }
+
+    #[test]
+    fn test_copy_wrapped_token() {
+        let mut editor = wrapped_editor();
+        editor.process_keypress(Key::PageDown).unwrap();
+        test_keys(editor, vec![Key::Right, Key::Right], "Running");
+    }
+}
+
+mod header {
+    use super::*;
+    use crate::screen::Screen;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(test_editor(TestFile::GetPodsAll).header, 1);
+        assert_eq!(test_editor(TestFile::GetPods).header, 0);
+        assert_eq!(test_editor(TestFile::PodYaml).header, 0);
+    }
+
+    #[test]
+    fn test_scroll_below_header() {
+        // 10 rows of text, 9 below the header
+        let mut editor = editor_with(TestFile::GetPodsAll.to_str(), 150, 12);
+        for _ in 0..15 {
+            editor.process_keypress(Key::Down).unwrap();
+        }
+        assert_eq!(editor.offset.y, 6);
+        for _ in 0..9 {
+            editor.process_keypress(Key::Up).unwrap();
+        }
+        assert_eq!(editor.offset.y, 5);
+        editor.process_keypress(Key::PageDown).unwrap();
+        assert_eq!(editor.cursor_position.y, 15);
+    }
+
+    #[test]
+    fn test_wrapped() {
+        // Every row takes 2 screen lines, leaving 6 below the header
+        let memory = Memory::new(60, 10);
+        let output = memory.output();
+        let mut editor = editor_on(TestFile::GetPodsAll.to_str(), memory);
+        editor.set_wrap(true);
+        for _ in 0..5 {
+            editor.process_keypress(Key::Down).unwrap();
+        }
+        // From the second screen line of row 2 to the first of row 5
+        assert_eq!((editor.offset.y, editor.wrap_offset), (1, 1));
+        editor.refresh_screen().unwrap();
+        let mut screen = Screen::new(60, 10);
+        screen.feed(&output.borrow());
+        // The header stays on top of the rows scrolled
+        assert_eq!(screen.line(0), "NAMESPACE     NAME");
+        assert!(screen.line(1).trim_start().starts_with("READY"));
+        assert!(screen.line(2).trim_start().starts_with("1/1"));
+        assert!(
+            screen
+                .line(3)
+                .ends_with("logdb-shared-ingest-756cfb4c58-mqvqr")
+        );
+        assert!(
+            screen
+                .line(7)
+                .ends_with("logdb-shared-query-7dbd46c867-mjkk2")
+        );
+    }
+
+    #[test]
+    fn test_column_copy_excludes_header() {
+        let mut keys = vec![Key::Char('v'), Key::Char('v'), Key::Down, Key::Down];
+        keys.extend([Key::Right; 8]);
+        test_key_seq(TestFile::GetPodsAll, keys, "logging  \nlogging  ");
+    }
+
+    #[test]
+    fn test_copy_header() {
+        let mut keys = vec![Key::Char('v'), Key::Char('v')];
+        keys.extend([Key::Right; 8]);
+        test_key_seq(TestFile::GetPodsAll, keys, "NAMESPACE");
+    }
+}
+
+mod unicode {
+    use super::*;
+
+    #[test]
+    fn test_token() {
+        test_key_seq(
+            TestFile::UnicodeGitStatus,
+            vec![Key::Down, Key::Down, Key::Down, Key::Right],
+            "docs/日本語.md",
+        );
+    }
+
+    #[test]
+    fn test_combining_characters() {
+        test_key_seq(
+            TestFile::UnicodeGitStatus,
+            vec![Key::Up, Key::Up, Key::Up, Key::Right, Key::Right],
+            "nai\u{308}ve/re\u{301}sume\u{301}.txt",
+        );
+        // A letter and its accent are a single column
+        let mut keys = vec![Key::Up, Key::Up, Key::Up, Key::Right, Key::Right];
+        keys.extend([Key::Char('v'), Key::Char('v')]);
+        keys.extend([Key::Right; 4]);
+        test_key_seq(TestFile::UnicodeGitStatus, keys, "nai\u{308}ve");
+    }
+
+    #[test]
+    fn test_block() {
+        let mut keys = vec![
+            Key::Down,
+            Key::Down,
+            Key::Down,
+            Key::Right,
+            Key::Char('v'),
+            Key::Char('v'),
+            Key::Down,
+        ];
+        keys.extend([Key::Right; 7]);
+        test_key_seq(TestFile::UnicodeGitStatus, keys, "docs/日本語\nsrc/café");
+    }
+
+    #[test]
+    fn test_search() {
+        let mut keys = vec![Key::Char('/')];
+        keys.extend("日本語.md".chars().map(Key::Char));
+        test_key_seq(TestFile::UnicodeGitStatus, keys, "日本語.md");
+    }
+
+    #[test]
+    fn test_stream_after_emoji() {
+        // Up from the top goes to the bottom, and the empty row is skipped
+        let mut keys = vec![Key::Up, Key::Up, Key::Up, Key::Up, Key::Right];
+        keys.extend([Key::Char('v'), Key::Char('s')]);
+        keys.extend([Key::Right; 6]);
+        test_key_seq(TestFile::UnicodeGitStatus, keys, "🎉 party");
+    }
+
+    #[test]
+    fn test_structure() {
+        let editor = editor_with("name: \"日本語 テキスト\"", 150, 150);
+        test_keys(
+            editor,
+            vec![Key::Right, Key::Char('+')],
+            "\"日本語 テキスト\"",
+        );
+    }
+
+    #[test]
+    fn test_scroll_wide_graphemes() {
+        let mut editor = editor_with(TestFile::UnicodeGitStatus.to_str(), 20, 10);
+        editor.document.set_tab_width(1);
+        for _ in 0..3 {
+            editor.process_keypress(Key::Down).unwrap();
+        }
+        editor.process_keypress(Key::Char('v')).unwrap();
+        // The tab and "modified:   docs/" take 18 columns, 日 2 more
+        for _ in 0..17 {
+            editor.process_keypress(Key::Right).unwrap();
+        }
+        assert_eq!(editor.offset.x, 0);
+        editor.process_keypress(Key::Right).unwrap();
+        assert_eq!(editor.offset.x, 2);
+    }
+
+    #[test]
+    fn test_scroll_tabs() {
+        let mut editor = editor_with("a\tb\tc\n", 12, 10);
+        editor.process_keypress(Key::Char('v')).unwrap();
+        // "a", the tab to column 8 and "b" take 9 columns, the next tab 7 more
+        for _ in 0..2 {
+            editor.process_keypress(Key::Right).unwrap();
+        }
+        assert_eq!(editor.offset.x, 0);
+        editor.process_keypress(Key::Right).unwrap();
+        assert_eq!(editor.offset.x, 2);
+    }
+}
+
+mod drawing {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_draw_changes_only() {
+        let memory = Memory::new(150, 150);
+        let output = memory.output();
+        let mut editor = editor_on(TestFile::GetPods.to_str(), memory);
+        let redrawn = |editor: &mut Editor| {
+            output.borrow_mut().clear();
+            editor.refresh_screen().unwrap();
+            output.borrow().matches(crate::ansi::CLEAR_LINE).count()
+        };
+        assert_eq!(redrawn(&mut editor), 150);
+        assert_eq!(redrawn(&mut editor), 0);
+
+        // The token highlighted moves to the next row, and the status bar
+        // counts another row.
+        editor.process_keypress(Key::Down).unwrap();
+        assert_eq!(redrawn(&mut editor), 3);
+    }
+
+    #[test]
+    fn test_run() {
+        let memory = Memory::new(150, 150).with_keys([Key::Down, Key::Char('\n')]);
+        let output = memory.output();
+        let mut editor = editor_on(TestFile::GetPods.to_str(), memory);
+        editor.run().unwrap();
+        assert_eq!(
+            editor.copied(),
+            Some("logdb-shared-ingest-756cfb4c58-h2cmm")
+        );
+        assert!(output.borrow().contains("Copied:"));
+    }
+
+    #[test]
+    fn test_wide_name_in_status_bar() {
+        let memory = Memory::new(11, 5);
+        let output = memory.output();
+        let mut editor = editor_on(TestFile::GetPods.to_str(), memory);
+        editor.set_name("日本語のファイル.txt".to_string());
+        editor.refresh_screen().unwrap();
+        assert!(output.borrow().contains("日本語のフ \x1b"));
+    }
+
+    #[test]
+    fn test_wide_search_in_message_bar() {
+        let memory = Memory::new(40, 5);
+        let output = memory.output();
+        let mut editor = editor_on(TestFile::GetPods.to_str(), memory);
+        editor.process_keypress(Key::Char('/')).unwrap();
+        for c in "日本語のファイル".chars() {
+            editor.process_keypress(Key::Char(c)).unwrap();
+        }
+        editor.refresh_screen().unwrap();
+        assert!(output.borrow().ends_with("navigate): 日"));
+    }
+
+    /// Times keys from being pressed to being drawn, on a large document.
+    /// Run with `cargo test --release bench_ -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_keystroke_latency() {
+        let text = TestFile::GetPodsAll.to_str().repeat(2000);
+        let mut editor = editor_with(&text, 200, 50);
+        let keys = [
+            Key::Down,
+            Key::Right,
+            Key::Char('v'),
+            Key::Right,
+            Key::Down,
+            Key::Esc,
+            Key::PageDown,
+            Key::Left,
+            Key::Up,
+        ];
+        let mut latencies: Vec<Duration> = Vec::new();
+        for key in keys.iter().cycle().take(5000) {
+            let start = Instant::now();
+            editor.process_keypress(*key).unwrap();
+            editor.refresh_screen().unwrap();
+            latencies.push(start.elapsed());
+        }
+        latencies.sort();
+        let mean = latencies.iter().sum::<Duration>() / latencies.len() as u32;
+        let p99 = latencies[latencies.len() * 99 / 100];
+        println!(
+            "{} rows, {} keys: mean {mean:?}, p99 {p99:?}, max {:?}",
+            text.lines().count(),
+            latencies.len(),
+            latencies[latencies.len() - 1]
+        );
+    }
+}
+
+mod snapshots {
+    use super::*;
+    use crate::screen::{Screen, assert_snapshot};
+
+    const WIDTH: u16 = 60;
+    const HEIGHT: u16 = 12;
+
+    /// What `output` puts on a fresh screen.
+    fn screen(output: &str) -> Screen {
+        let mut screen = Screen::new(WIDTH, HEIGHT);
+        screen.feed(output);
+        screen
+    }
+
+    /// Draws the editor after every key, and compares the screen with the
+    /// snapshot `name`. The lines left over from earlier frames have to
+    /// match a frame drawn from scratch.
+    fn test_screen(name: &str, test_file: TestFile, keys: &[Key]) {
+        let memory = Memory::new(WIDTH, HEIGHT);
+        let output = memory.output();
+        let mut editor = editor_on(test_file.to_str(), memory);
+        editor.refresh_screen().unwrap();
+        for &key in keys {
+            editor.process_keypress(key).unwrap();
+            editor.refresh_screen().unwrap();
+        }
+        let incremental = screen(&output.borrow());
+
+        output.borrow_mut().clear();
+        editor.terminal.clear_screen().unwrap();
+        editor.refresh_screen().unwrap();
+        assert_eq!(incremental, screen(&output.borrow()));
+
+        assert_snapshot(&format!("{name}.txt"), &incremental.to_string());
+    }
+
+    #[test]
+    fn test_get_pods() {
+        test_screen(
+            "get-pods",
+            TestFile::GetPods,
+            &[Key::Down, Key::Right, Key::Right],
+        );
+    }
+
+    #[test]
+    fn test_get_pods_all_page_down() {
+        test_screen(
+            "get-pods-all-page-down",
+            TestFile::GetPodsAll,
+            &[Key::PageDown, Key::Down],
+        );
+    }
+
+    #[test]
+    fn test_get_ns_visual_block() {
+        test_screen(
+            "get-ns-visual-block",
+            TestFile::GetNs,
+            &[
+                Key::Char('v'),
+                Key::Char('v'),
+                Key::Down,
+                Key::Down,
+                Key::Right,
+                Key::Right,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_pod_yaml_search() {
+        let mut keys = vec![Key::Char('/')];
+        keys.extend("logdb".chars().map(Key::Char));
+        test_screen("pod-yaml-search", TestFile::PodYaml, &keys);
+    }
+
+    #[test]
+    fn test_csv_line_numbers() {
+        test_screen(
+            "csv-line-numbers",
+            TestFile::Csv,
+            &[Key::Char('#'), Key::Down, Key::Down],
+        );
+    }
+
+    #[test]
+    fn test_git_status_wrap() {
+        test_screen(
+            "git-status-wrap",
+            TestFile::GitStatus,
+            &[Key::Char('W'), Key::Down],
+        );
+    }
+
+    #[test]
+    fn test_visual_git_status_lines() {
+        test_screen(
+            "visual-git-status-lines",
+            TestFile::VisualGitStatus,
+            &[Key::Down, Key::Char('V'), Key::Down],
+        );
+    }
+
+    #[test]
+    fn test_unicode_git_status() {
+        test_screen(
+            "unicode-git-status",
+            TestFile::UnicodeGitStatus,
+            &[Key::Down, Key::Down, Key::Down, Key::Right],
+        );
+    }
+}
+
+mod empty {
+    use super::*;
+
+    #[test]
+    fn test_no_tokens() {
+        let mut editor = editor_with("\n   \n\n", 150, 150);
+        for key in [Key::Down, Key::Up, Key::PageDown, Key::PageUp, Key::Right] {
+            editor.process_keypress(key).unwrap();
+        }
+        editor.process_keypress(Key::Char('\n')).unwrap();
+        assert_eq!(editor.should_quit, ShouldQuit::Ye(CopyStatus::Noop));
+    }
+
+    #[test]
+    fn test_no_tokens_search_and_visual() {
+        let mut editor = editor_with("\n   \n\n", 150, 150);
+        for key in [Key::Char('/'), Key::Char('x'), Key::Down, Key::Esc] {
+            editor.process_keypress(key).unwrap();
+        }
+        for key in [Key::Char('v'), Key::Down, Key::Right, Key::Char('v')] {
+            editor.process_keypress(key).unwrap();
+        }
+        assert_eq!(editor.input_mode, InputMode::Visual(VisualMode::Block));
+        assert_eq!(editor.cursor_position.y, 1);
+    }
+
+    #[test]
+    fn test_block_past_end_of_row() {
+        let mut keys = vec![Key::Char('v'), Key::Char('v'), Key::Down];
+        keys.extend(vec![Key::Right; 3]);
+        test_keys(editor_with("abcdef\nab\n", 150, 150), keys, "abcd\nab");
+    }
+
+    #[test]
+    fn test_skip_rows_without_tokens() {
+        test_keys(editor_with("a\n\n  \nb\n", 150, 150), vec![Key::Down], "b");
+        test_keys(
+            editor_with("a\n\n  \nb\n", 150, 150),
+            vec![Key::Down, Key::Down],
+            "a",
+        );
+        test_keys(editor_with("a\n\n  \nb\n", 150, 150), vec![Key::Up], "b");
+    }
+
+    #[test]
+    fn test_page_up_skips_rows_without_tokens_upwards() {
+        // Pages of 3 rows
+        let editor = editor_with("a\nb\n\nc\nd\ne\n", 20, 5);
+        let mut keys = vec![Key::Down; 4];
+        keys.push(Key::PageUp);
+        test_keys(editor, keys, "b");
+    }
+}
+
+mod output {
+    use super::*;
+    use crate::output::OutputFormat;
+
+    #[test]
+    fn test_block_as_json() {
+        let mut editor = test_editor(TestFile::GetNs);
+        editor.set_output_format(OutputFormat {
+            trim: true,
+            json: true,
+            ..Default::default()
+        });
+        let mut keys = vec![
+            Key::Down,
+            Key::Down,
+            Key::Char('v'),
+            Key::Char('v'),
+            Key::Down,
+        ];
+        keys.extend(vec![Key::Right; 11]);
+        test_keys(editor, keys, r#"["david-test-3", "foobar-1"]"#);
+    }
+}
+
+mod command {
+    use super::*;
+    use crate::command::{Action, CommandTemplate};
+
+    fn command_editor(action: Action, template: &str) -> Editor {
+        let mut editor = test_editor(TestFile::GetPods);
+        editor.set_commands(vec![CommandTemplate {
+            key: 'x',
+            action,
+            template: template.to_string(),
+        }]);
+        editor
+    }
+
+    #[test]
+    fn test_exec() {
+        let mut editor = command_editor(Action::Exec, "kubectl logs {}");
+        for key in [Key::Down, Key::Char('!'), Key::Char('x')] {
+            editor.process_keypress(key).unwrap();
+        }
+        assert_eq!(
+            editor.exec_command(),
+            Some("kubectl logs logdb-shared-ingest-756cfb4c58-h2cmm")
+        );
+    }
+
+    #[test]
+    fn test_unbound_key() {
+        let mut editor = command_editor(Action::Exec, "kubectl logs {}");
+        for key in [Key::Char('!'), Key::Char('y')] {
+            editor.process_keypress(key).unwrap();
+        }
+        assert_eq!(editor.input_mode, InputMode::Normal);
+        assert_eq!(editor.should_quit, ShouldQuit::No);
+    }
+
+    #[test]
+    fn test_view_and_back() {
+        let mut editor = command_editor(Action::View, "echo pod {} is ready");
+        for key in [Key::Down, Key::Char('!'), Key::Char('x')] {
+            editor.process_keypress(key).unwrap();
+        }
+        assert_eq!(editor.document.len(), 1);
+        for key in [Key::Right, Key::Esc] {
+            editor.process_keypress(key).unwrap();
+        }
+        assert_eq!(editor.document.len(), 19);
+        test_keys(editor, vec![Key::Right], "1/1");
+    }
+
+    #[test]
+    fn test_view_copy() {
+        let mut editor = command_editor(Action::View, "echo pod {} is ready");
+        for key in [Key::Char('!'), Key::Char('x'), Key::Right] {
+            editor.process_keypress(key).unwrap();
+        }
+        test_keys(editor, vec![], "logdb-shared-ingest-756cfb4c58-68pgk");
+    }
+}
+
+mod buffers {
+    use super::*;
+
+    fn document(test_file: TestFile) -> Document {
+        Document::new(test_file.to_str().as_bytes()).unwrap()
+    }
+
+    fn buffers_editor() -> Editor {
+        let mut editor = test_editor(TestFile::GetPods);
+        editor.set_name("pods.txt".to_string());
+        editor.add_buffer("pod.yaml".to_string(), document(TestFile::PodYaml));
+        editor.add_buffer("status.txt".to_string(), document(TestFile::GitStatus));
+        editor
+    }
+
+    #[test]
+    fn test_next_and_previous() {
+        let mut editor = buffers_editor();
+        editor.process_keypress(Key::Char('\t')).unwrap();
+        assert_eq!(editor.name(), Some("pod.yaml"));
+        assert_eq!(editor.buffer, 1);
+        editor.process_keypress(Key::BackTab).unwrap();
+        editor.process_keypress(Key::BackTab).unwrap();
+        assert_eq!(editor.name(), Some("status.txt"));
+        assert_eq!(editor.buffer, 2);
+        test_keys(editor, vec![], "On");
+    }
+
+    #[test]
+    fn test_header_in_every_file() {
+        let mut editor = buffers_editor();
+        editor.set_header(0);
+        editor.add_buffer("pods.txt".to_string(), document(TestFile::GetPods));
+        for _ in 0..3 {
+            editor.process_keypress(Key::Char('\t')).unwrap();
+            assert_eq!(editor.header, 0);
+        }
+        assert_eq!(editor.name(), Some("pods.txt"));
+        assert_eq!(editor.buffer, 3);
+    }
+
+    #[test]
+    fn test_ex_commands() {
+        let mut editor = buffers_editor();
+        for key in [
+            Key::Char(':'),
+            Key::Char('b'),
+            Key::Char('n'),
+            Key::Char('\n'),
+        ] {
+            editor.process_keypress(key).unwrap();
+        }
+        assert_eq!(editor.name(), Some("pod.yaml"));
+        assert_eq!(editor.input_mode, InputMode::Normal);
+        for key in [
+            Key::Char(':'),
+            Key::Char('b'),
+            Key::Char('p'),
+            Key::Char('\n'),
+        ] {
+            editor.process_keypress(key).unwrap();
+        }
+        assert_eq!(editor.name(), Some("pods.txt"));
+        for key in [Key::Char(':'), Key::Char('q'), Key::Char('\n')] {
+            editor.process_keypress(key).unwrap();
+        }
+        assert_eq!(editor.status_message, "Not a command: q");
+    }
+
+    #[test]
+    fn test_state_is_kept() {
+        let mut editor = buffers_editor();
+        for key in [Key::Down, Key::Right, Key::Char('\t'), Key::Char('/')] {
+            editor.process_keypress(key).unwrap();
+        }
+        for key in [Key::Char('k'), Key::Char('i'), Key::Char('\t')] {
+            editor.process_keypress(key).unwrap();
+        }
+        editor.process_keypress(Key::Char('\t')).unwrap();
+        assert_eq!(editor.name(), Some("pods.txt"));
+        assert_eq!(editor.input_mode, InputMode::Normal);
+        editor.process_keypress(Key::Char('\t')).unwrap();
+        assert_eq!(editor.status_message, format!("{SEARCH_STRING}ki"));
+        for key in [Key::Char('n'), Key::Char('d')] {
+            editor.process_keypress(key).unwrap();
+        }
+        test_keys(editor, vec![], "kind");
+    }
+
+    #[test]
+    fn test_tokenizer_is_kept() {
+        let mut editor = buffers_editor();
+        editor.process_keypress(Key::Char('t')).unwrap();
+        for key in [
+            Key::Char('-'),
+            Key::Char('\n'),
+            Key::Char('\t'),
+            Key::BackTab,
+        ] {
+            editor.process_keypress(key).unwrap();
+        }
+        test_keys(editor, vec![], "logdb");
+    }
+
+    #[test]
+    fn test_single_file() {
+        let mut editor = test_editor(TestFile::GetPods);
+        editor.process_keypress(Key::Char('\t')).unwrap();
+        assert_eq!(editor.status_message, "There are no other files.");
+        test_keys(editor, vec![], "logdb-shared-ingest-756cfb4c58-68pgk");
+    }
+}
+
+mod copies {
+    use super::*;
+
+    fn history_editor() -> Editor {
+        let entries = [
+            ("now  stdin  a\\nb", "a\nb"),
+            ("1m ago  k  \\n\\nc", "\n\nc"),
+        ];
+        let rows: String = entries.iter().map(|(row, _)| format!("{row}\n")).collect();
+        let mut editor = editor_with(&rows, 150, 150);
+        editor.document.update_tokenizer(Tokenizer::Line);
+        editor.set_copies(entries.iter().map(|(_, copy)| copy.to_string()).collect());
+        editor
+    }
+
+    #[test]
+    fn test_row_copies_its_entry() {
+        test_keys(history_editor(), vec![], "a\nb");
+        test_keys(history_editor(), vec![Key::Down], "\n\nc");
+    }
+
+    #[test]
+    fn test_line_selection_copies_entries() {
+        let keys = vec![Key::Char('V'), Key::Down];
+        test_keys(history_editor(), keys, "a\nb\n\n\nc");
+    }
+
+    #[test]
+    fn test_token_copies_itself() {
+        let keys = vec![Key::Char('w'), Key::Right];
+        test_keys(history_editor(), keys, "stdin");
+    }
+
+    #[test]
+    fn test_block_selection_copies_rows() {
+        let keys = vec![Key::Char('v'), Key::Char('v'), Key::Right, Key::Right];
+        test_keys(history_editor(), keys, "now");
+    }
+}
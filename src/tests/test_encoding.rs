@@ -0,0 +1,58 @@
+use super::*;
+
+fn utf16le(text: &str) -> Vec<u8> {
+    let mut bytes = vec![0xff, 0xfe];
+    bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+    bytes
+}
+
+#[test]
+fn test_parse() {
+    assert_eq!(Encoding::parse("UTF-8"), Some(Encoding::Utf8));
+    assert_eq!(Encoding::parse("utf-16le"), Some(Encoding::Utf16Le));
+    assert_eq!(Encoding::parse("iso-8859-1"), Some(Encoding::Latin1));
+    assert_eq!(Encoding::parse("CP1252"), Some(Encoding::Windows1252));
+    assert_eq!(Encoding::parse("ebcdic"), None);
+}
+
+#[test]
+fn test_detect() {
+    assert_eq!(Encoding::detect(b"plain ascii\n"), Encoding::Utf8);
+    assert_eq!(Encoding::detect("café\n".as_bytes()), Encoding::Utf8);
+    assert_eq!(Encoding::detect(b"caf\xe9\n"), Encoding::Windows1252);
+    // A stray byte among UTF-8 text is a broken character, not Latin-1
+    assert_eq!(Encoding::detect(b"caf\xc3\xa9 \xff\n"), Encoding::Utf8);
+    assert_eq!(Encoding::detect(&utf16le("a")), Encoding::Utf16Le);
+    assert_eq!(Encoding::detect(&[0xfe, 0xff, 0, b'a']), Encoding::Utf16Be);
+    assert_eq!(Encoding::detect(b""), Encoding::Utf8);
+}
+
+#[test]
+fn test_decode() {
+    assert_eq!(Encoding::Utf8.decode(b"\xef\xbb\xbfNAME"), "NAME");
+    assert_eq!(Encoding::Utf8.decode(b"bad \xff byte"), "bad \u{fffd} byte");
+    assert_eq!(Encoding::Latin1.decode(b"caf\xe9"), "café");
+    // Latin-1 has control characters where Windows-1252 has quotes
+    assert_eq!(Encoding::Latin1.decode(b"\x93 \x81"), "\u{93} \u{81}");
+    assert_eq!(Encoding::Windows1252.decode(b"caf\xe9"), "café");
+    assert_eq!(
+        Encoding::Windows1252.decode(b"\x93quoted\x94 \x81"),
+        "“quoted” \u{fffd}"
+    );
+}
+
+#[test]
+fn test_transcode() {
+    assert_eq!(
+        Encoding::Utf16Le.transcode(&utf16le("a\r\nβ\n")),
+        "a\r\nβ\n".as_bytes()
+    );
+    assert_eq!(
+        Encoding::Utf16Be.transcode(&[0, b'a', 0xd8, 0x00, 0, b'b']),
+        "a\u{fffd}b".as_bytes()
+    );
+    assert_eq!(
+        Encoding::Utf16Le.transcode(&[b'a', 0, b'b']),
+        "a\u{fffd}".as_bytes()
+    );
+}
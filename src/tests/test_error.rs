@@ -0,0 +1,35 @@
+use std::collections::HashSet;
+
+use super::*;
+
+#[test]
+fn test_display_is_one_line() {
+    let e = Error::Io(
+        "missing.txt".to_string(),
+        io::Error::new(io::ErrorKind::NotFound, "No such file or directory"),
+    );
+    assert_eq!(
+        e.to_string(),
+        "Failed to read missing.txt: No such file or directory"
+    );
+    let e = Error::Config("lcp.toml: line 3: expected `=`".to_string());
+    assert_eq!(e.to_string(), "lcp.toml: line 3: expected `=`");
+}
+
+#[test]
+fn test_exit_codes_are_distinct() {
+    let io_error = || io::Error::other("oops");
+    let errors = [
+        Error::Io("stdin".to_string(), io_error()),
+        Error::Usage(String::new()),
+        Error::Empty(String::new()),
+        Error::Config(String::new()),
+        Error::Write("out.txt".to_string(), io_error()),
+        Error::Terminal(io_error()),
+        Error::Clipboard(String::new()),
+        Error::Command("true".to_string(), io_error()),
+    ];
+    let codes: HashSet<i32> = errors.iter().map(Error::exit_code).collect();
+    assert!(!codes.contains(&0));
+    assert_eq!(codes.len(), errors.len());
+}
@@ -0,0 +1,82 @@
+use super::*;
+
+fn test_history(name: &str) -> History {
+    let path = std::env::temp_dir().join(format!("lcp-history-{name}-{}", std::process::id()));
+    let _ = fs::remove_file(&path);
+    History::new(path)
+}
+
+#[test]
+fn test_missing_file() {
+    let history = test_history("missing");
+    assert_eq!(history.entries().unwrap(), vec![]);
+    assert_eq!(render(&[], 0), "");
+}
+
+#[test]
+fn test_most_recent_first() {
+    let history = test_history("recent");
+    history.push("stdin", "first").unwrap();
+    history.push("pods.txt", "second").unwrap();
+    let entries = history.entries().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].source, "pods.txt");
+    assert_eq!(entries[0].text, "second");
+    assert_eq!(entries[1].source, "stdin");
+    assert_eq!(entries[1].text, "first");
+}
+
+#[test]
+fn test_duplicate_moves_to_front() {
+    let history = test_history("duplicate");
+    history.push("stdin", "first").unwrap();
+    history.push("stdin", "second").unwrap();
+    history.push("stdin", "first").unwrap();
+    let texts: Vec<String> = history
+        .entries()
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.text)
+        .collect();
+    assert_eq!(texts, ["first", "second"]);
+}
+
+#[test]
+fn test_escaping() {
+    let history = test_history("escaping");
+    let text = "foo\tbar\nbaz\\n";
+    history.push("a\tb", text).unwrap();
+    let entries = history.entries().unwrap();
+    assert_eq!(entries[0].source, "a\tb");
+    assert_eq!(entries[0].text, text);
+}
+
+#[test]
+fn test_truncates() {
+    let history = test_history("truncates");
+    for i in 0..HISTORY_SIZE + 5 {
+        history.push("stdin", &i.to_string()).unwrap();
+    }
+    let entries = history.entries().unwrap();
+    assert_eq!(entries.len(), HISTORY_SIZE);
+    assert_eq!(entries[0].text, (HISTORY_SIZE + 4).to_string());
+}
+
+#[test]
+fn test_render() {
+    const NOW: u64 = 1_700_000_000;
+    let entry = |timestamp, source: &str, text: &str| Entry {
+        timestamp,
+        source: source.to_string(),
+        text: text.to_string(),
+    };
+    let entries = [
+        entry(NOW, "stdin", "a\nb"),
+        entry(NOW - 90, "pods.txt", "\n\nc"),
+        entry(NOW - 3 * 86400, "k", "d"),
+    ];
+    assert_eq!(
+        render(&entries, NOW),
+        "    now  stdin     a\\nb\n 1m ago  pods.txt  \\n\\nc\n 3d ago  k         d\n"
+    );
+}
@@ -0,0 +1,86 @@
+use super::*;
+
+fn lines(text: &str) -> Lines {
+    Lines::read(text.as_bytes()).unwrap()
+}
+
+#[test]
+fn test_get() {
+    let lines = lines("a\n\nc\r\nd");
+    assert_eq!(lines.get(0), Some(&b"a"[..]));
+    assert_eq!(lines.get(1), Some(&b""[..]));
+    assert_eq!(lines.get(2), Some(&b"c\r"[..]));
+    assert_eq!(lines.get(3), Some(&b"d"[..]));
+    assert_eq!(lines.get(4), None);
+    assert_eq!(lines.len(), 4);
+}
+
+#[test]
+fn test_trailing_newline() {
+    assert_eq!(lines("a\nb\n").len(), 2);
+    assert_eq!(lines("").len(), 0);
+    assert_eq!(lines("\n").len(), 1);
+}
+
+#[test]
+fn test_found_lazily() {
+    let text: String = (0..3 * STRIDE).map(|i| format!("line {i}\n")).collect();
+    let lines = lines(&text);
+    assert_eq!(lines.found(), (0, false));
+    assert_eq!(lines.get(5), Some(&b"line 5"[..]));
+    assert_eq!(lines.found(), (6, false));
+    // Between the kept offsets
+    assert_eq!(
+        lines.get(STRIDE + 7),
+        Some(format!("line {}", STRIDE + 7).as_bytes())
+    );
+    assert_eq!(
+        lines.get(2 * STRIDE),
+        Some(format!("line {}", 2 * STRIDE).as_bytes())
+    );
+    assert_eq!(lines.len(), 3 * STRIDE);
+    assert_eq!(lines.found(), (3 * STRIDE, true));
+    assert_eq!(
+        lines.get(3 * STRIDE - 1),
+        Some(format!("line {}", 3 * STRIDE - 1).as_bytes())
+    );
+}
+
+#[test]
+fn test_map() {
+    let path = std::env::temp_dir().join(format!("lcp-test-lines-{}", std::process::id()));
+    std::fs::write(&path, "mapped\nfile").unwrap();
+    let lines = Lines::map(&File::open(&path).unwrap()).unwrap();
+    assert_eq!(lines.get(1), Some(&b"file"[..]));
+    std::fs::write(&path, "").unwrap();
+    let lines = Lines::map(&File::open(&path).unwrap()).unwrap();
+    assert_eq!(lines.len(), 0);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_forward_and_backward() {
+    let lines = lines("a\n\nc\nd\n");
+    let forward: Vec<(usize, &[u8])> = lines.forward(1).collect();
+    assert_eq!(forward, [(1, &b""[..]), (2, b"c"), (3, b"d")]);
+    assert_eq!(lines.forward(4).count(), 0);
+    let backward: Vec<(usize, &[u8])> = lines.backward(2).collect();
+    assert_eq!(backward, [(1, &b""[..]), (0, b"a")]);
+    let backward: Vec<usize> = lines.backward(4).map(|(i, _)| i).collect();
+    assert_eq!(backward, [3, 2, 1, 0]);
+    assert_eq!(lines.backward(0).count(), 0);
+
+    let lines = self::lines("a\nb");
+    let backward: Vec<(usize, &[u8])> = lines.backward(2).collect();
+    assert_eq!(backward, [(1, &b"b"[..]), (0, b"a")]);
+}
+
+#[test]
+fn test_walks_scan_once() {
+    let text: String = (0..4 * STRIDE).map(|i| format!("line {i}\n")).collect();
+    let lines = lines(&text);
+    assert_eq!(lines.forward(STRIDE / 2).count(), 4 * STRIDE - STRIDE / 2);
+    assert_eq!(lines.backward(lines.len()).count(), 4 * STRIDE);
+    // Finding the lines, walking forward and walking back
+    assert!(lines.scanned.get() <= 3 * text.len());
+}
@@ -0,0 +1,54 @@
+use super::*;
+
+const BLOCK: &str = "nginx-1  \ncoredns  \nmy pod   ";
+
+#[test]
+fn test_default_is_verbatim() {
+    assert_eq!(OutputFormat::default().apply(BLOCK), BLOCK);
+}
+
+#[test]
+fn test_join_and_trim() {
+    let format = OutputFormat {
+        separator: Some(" ".to_string()),
+        trim: true,
+        ..Default::default()
+    };
+    assert_eq!(format.apply(BLOCK), "nginx-1 coredns my pod");
+}
+
+#[test]
+fn test_quote() {
+    let format = OutputFormat {
+        trim: true,
+        quote: true,
+        ..Default::default()
+    };
+    assert_eq!(format.apply(BLOCK), "nginx-1\ncoredns\n'my pod'");
+}
+
+#[test]
+fn test_template() {
+    let format = OutputFormat {
+        separator: Some(", ".to_string()),
+        trim: true,
+        template: Some("\"{}\"".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(format.apply(BLOCK), "\"nginx-1\", \"coredns\", \"my pod\"");
+}
+
+#[test]
+fn test_json() {
+    let format = OutputFormat {
+        trim: true,
+        json: true,
+        ..Default::default()
+    };
+    assert_eq!(format.apply(BLOCK), r#"["nginx-1", "coredns", "my pod"]"#);
+}
+
+#[test]
+fn test_json_string() {
+    assert_eq!(json_string("a\"b\\c\td\u{1}"), r#""a\"b\\c\td\u0001""#);
+}
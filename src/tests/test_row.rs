@@ -30,3 +30,148 @@ fn test_row_starts_whitespace() {
     let row = Row::new(s, &Tokenizer::Whitespace);
     assert_eq!(row.tokens, expected);
 }
+
+#[test]
+fn test_row_line_tokenizer() {
+    let row = Row::new("  kubectl get po  ", &Tokenizer::Line);
+    assert_eq!(row.tokens, vec![Token { start: 2, len: 14 }]);
+    let row = Row::new("   ", &Tokenizer::Line);
+    assert_eq!(row.tokens, vec![]);
+}
+
+#[test]
+fn test_wrap() {
+    let row = Row::new("abcdefghij", &Tokenizer::Whitespace);
+    assert_eq!(row.wrap(4), vec![(0, 4), (4, 8), (8, 12)]);
+    assert_eq!(row.wrap(20), vec![(0, 20)]);
+    let row = Row::new("", &Tokenizer::Whitespace);
+    assert_eq!(row.wrap(4), vec![(0, 4)]);
+}
+
+#[test]
+fn test_wrap_wide_graphemes() {
+    // Two columns per ideograph, and an e with a combining accent
+    let row = Row::new("日本語e\u{301}x", &Tokenizer::Whitespace);
+    assert_eq!(row.wrap(3), vec![(0, 1), (1, 2), (2, 4), (4, 7)]);
+}
+
+#[test]
+fn test_multibyte_columns() {
+    let row = Row::new("猫 café e\u{301}t\u{e9} 🎉", &Tokenizer::Whitespace);
+    assert_eq!(
+        row.tokens,
+        vec![
+            Token { start: 0, len: 1 },
+            Token { start: 2, len: 4 },
+            Token { start: 7, len: 3 },
+            Token { start: 11, len: 1 },
+        ]
+    );
+    assert_eq!((row.len, row.text_len()), (12, 12));
+    assert_eq!(row.slice(7, 10), "e\u{301}t\u{e9}");
+    assert_eq!(row.find("🎉", 0, SearchDirection::Forward), Some(11));
+    assert_eq!(row.find("é", 12, SearchDirection::Backward), Some(9));
+    assert_eq!(row.width(0, 12), 14);
+}
+
+#[test]
+fn test_render_width() {
+    let row = Row::new("日本語", &Tokenizer::Whitespace);
+    let reset = Bg(Color::Reset).to_string();
+    assert_eq!(row.render(0, usize::MAX, 5, (0, 0)), format!("日本{reset}"));
+    assert_eq!(row.render(1, usize::MAX, 5, (0, 0)), format!("本語{reset}"));
+}
+
+#[test]
+fn test_virtual_columns() {
+    let row = Row::new("ab", &Tokenizer::Whitespace);
+    assert_eq!(row.string, "ab");
+    assert_eq!(row.width(0, 5), 5);
+    let (on, off) = (
+        Bg(HIGHLIGHTING_COLOR).to_string(),
+        Bg(Color::Reset).to_string(),
+    );
+    // Only the highlighted virtual columns are drawn
+    assert_eq!(
+        row.render(0, usize::MAX, 10, (1, 4)),
+        format!("a{on}b  {off}")
+    );
+}
+
+#[test]
+fn test_row_regex_tokenizer() {
+    let tokenizer = Tokenizer::Regex(regex::Regex::new(r"\d+[hd]").unwrap());
+    let row = Row::new("ingest  1/1  Running  0  2d20h", &tokenizer);
+    assert_eq!(
+        row.tokens,
+        vec![Token { start: 25, len: 2 }, Token { start: 27, len: 3 }]
+    );
+}
+
+#[test]
+fn test_control_characters() {
+    let row = Row::new("a\u{0}b\u{1b}[31mc\u{7f}\u{9b}", &Tokenizer::Whitespace);
+    let reset = Bg(Color::Reset).to_string();
+    assert_eq!(
+        row.render(0, usize::MAX, 20, (0, 0)),
+        format!("a^@b^[[31mc^?<9B>{reset}")
+    );
+    assert_eq!(row.width(0, 2), 3);
+    // The text stays as it is, to be copied
+    assert_eq!(row.slice(3, 4), "\u{1b}");
+}
+
+#[test]
+fn test_overstrike() {
+    let row = Row::new("N\u{8}NA\u{8}AME  _\u{8}l_\u{8}s", &Tokenizer::Whitespace);
+    assert_eq!(row.string, "NAME  ls");
+    assert_eq!(
+        row.tokens,
+        vec![Token { start: 0, len: 4 }, Token { start: 6, len: 2 }]
+    );
+    let (bold, no_bold) = (ansi::BOLD, ansi::NO_BOLD);
+    let (underline, no_underline) = (ansi::UNDERLINE, ansi::NO_UNDERLINE);
+    let reset = Bg(Color::Reset).to_string();
+    assert_eq!(
+        row.render(0, usize::MAX, 20, (0, 0)),
+        format!("{bold}NA{no_bold}ME  {underline}ls{no_underline}{reset}")
+    );
+
+    // Written over after a carriage return, but not bold
+    let row = Row::new("50% [==  ]\r100% [====]", &Tokenizer::Whitespace);
+    assert_eq!(row.string, "100% [====]");
+    assert!(row.emphasis.is_empty());
+
+    // Nothing to step back over
+    assert_eq!(Row::new("\u{8}a", &Tokenizer::Whitespace).string, "a");
+}
+
+#[test]
+fn test_tab_stops() {
+    let row = Row::new("NAME\tAGE", &Tokenizer::Whitespace);
+    assert_eq!(row.column(4), 4);
+    assert_eq!(row.column(5), 8);
+    assert_eq!(row.width(0, 5), 8);
+    assert_eq!(row.width(4, 5), 4);
+    let reset = Bg(Color::Reset).to_string();
+    assert_eq!(
+        row.render(0, usize::MAX, 20, (0, 0)),
+        format!("NAME    AGE{reset}")
+    );
+    // Tab stops are counted from the start of the row, not of the screen
+    assert_eq!(row.render(2, 6, 20, (0, 0)), format!("ME    A{reset}"));
+    // The tab is kept for copying
+    assert_eq!(row.slice(3, 6), "E\tA");
+
+    let row = Row::new("a\tb", &Tokenizer::Whitespace).with_tab_width(4);
+    assert_eq!(row.width(0, 3), 5);
+    assert_eq!(row.wrap(4), vec![(0, 2), (2, 6)]);
+}
+
+#[test]
+fn test_truncate() {
+    assert_eq!(truncate("abc", 2), "ab");
+    assert_eq!(truncate("abc", 5), "abc");
+    assert_eq!(truncate("日本語", 5), "日本");
+    assert_eq!(truncate("e\u{301}e\u{301}", 1), "e\u{301}");
+}
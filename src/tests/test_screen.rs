@@ -0,0 +1,49 @@
+use super::*;
+use crate::ansi::{self, Bg, Fg};
+
+#[test]
+fn test_goto_and_clear() {
+    let mut screen = Screen::new(10, 3);
+    screen.feed(&format!("{}abc{}xy", ansi::goto(2, 1), ansi::goto(0, 2)));
+    assert_eq!(screen.line(1), "  abc");
+    assert_eq!(screen.line(2), "xy");
+    screen.feed(&format!("{}{}z", ansi::goto(0, 1), ansi::CLEAR_LINE));
+    assert_eq!(screen.line(1), "z");
+    screen.feed(ansi::CLEAR_SCREEN);
+    assert_eq!(screen.to_string(), "|\n|\n|\n");
+}
+
+#[test]
+fn test_lines() {
+    let mut screen = Screen::new(10, 3);
+    screen.feed("Copied:\r\n\r\nab\r\n");
+    assert_eq!(screen.line(0), "Copied:");
+    assert_eq!(screen.line(2), "ab");
+}
+
+#[test]
+fn test_colors() {
+    let mut screen = Screen::new(10, 1);
+    screen.feed(&format!(
+        "a{}b{}c{}d{}e{}f",
+        Bg(ansi::Color::LIGHT_WHITE),
+        Bg(ansi::Color::Reset),
+        ansi::BOLD,
+        ansi::NO_BOLD,
+        Fg(ansi::Color::Rgb(1, 2, 3))
+    ));
+    assert_eq!(screen.cell(1, 0).style.bg, Some(Color::Indexed(15)));
+    assert_eq!(screen.cell(2, 0).style, Style::default());
+    assert!(screen.cell(3, 0).style.bold);
+    assert_eq!(screen.to_string(), "|abcdef\n: ^ * ~\n");
+    assert_eq!(screen.cell(5, 0).style.fg, Some(Color::Rgb(1, 2, 3)));
+}
+
+#[test]
+fn test_wide_characters() {
+    let mut screen = Screen::new(5, 1);
+    screen.feed("日本語");
+    // The last one doesn't fit
+    assert_eq!(screen.line(0), "日本");
+    assert_eq!(screen.cell(1, 0).text, "");
+}
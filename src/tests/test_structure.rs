@@ -0,0 +1,156 @@
+use super::*;
+
+fn document(s: &str) -> Document {
+    Document::new(s.as_bytes()).unwrap()
+}
+
+fn text(document: &Document, span: &Span) -> String {
+    let mut lines = Vec::new();
+    for y in span.start.0..=span.end.0 {
+        let bytes = bytes(document, y);
+        let start = if y == span.start.0 { span.start.1 } else { 0 };
+        let end = if y == span.end.0 {
+            span.end.1 + 1
+        } else {
+            bytes.len()
+        };
+        lines.push(String::from_utf8_lossy(&bytes[start.min(end)..end]).to_string());
+    }
+    lines.join("\n")
+}
+
+/// Expands from the token `x` of row `y` until there's nothing bigger.
+fn expansions(document: &Document, x: usize, y: usize) -> Vec<(Kind, String)> {
+    let mut span = token(document, x, y).unwrap();
+    let mut result = vec![(span.kind, text(document, &span))];
+    while let Some(bigger) = expand(document, &span) {
+        result.push((bigger.kind, text(document, &bigger)));
+        span = bigger;
+    }
+    result
+}
+
+#[test]
+fn test_yaml() {
+    let doc = document(include_str!("files/pod.yaml"));
+    let expansions = expansions(&doc, 1, 4);
+    let kinds: Vec<Kind> = expansions.iter().map(|(kind, _)| *kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            Kind::Token,
+            Kind::Pair,
+            Kind::Subtree,
+            Kind::Subtree,
+            Kind::Paragraph
+        ]
+    );
+    assert_eq!(expansions[0].1, "\"false\"");
+    assert_eq!(
+        expansions[1].1,
+        "cluster-autoscaler.kubernetes.io/safe-to-evict: \"false\""
+    );
+    assert!(expansions[2].1.starts_with("annotations:\n    cluster"));
+    assert!(expansions[2].1.ends_with("prometheus.io/scrape: \"true\""));
+    assert!(expansions[3].1.starts_with("metadata:\n  annotations:"));
+    assert!(
+        expansions[3]
+            .1
+            .ends_with("creationTimestamp: \"2023-09-18T21:16:04Z\"")
+    );
+    assert!(expansions[4].1.starts_with("apiVersion: v1"));
+}
+
+#[test]
+fn test_yaml_sequence_item() {
+    let doc = document(include_str!("files/pod.yaml"));
+    let expansions = expansions(&doc, 1, 15);
+    assert_eq!(expansions[1], (Kind::Pair, "status: \"True\"".to_string()));
+    assert_eq!(
+        expansions[2],
+        (
+            Kind::Subtree,
+            "- lastProbeTime: null
+    lastTransitionTime: null
+    reason: LoadBalancerNegWithoutHealthCheck
+    status: \"True\"
+    type: cloud.google.com/load-balancer-neg-ready"
+                .to_string()
+        )
+    );
+    assert!(
+        expansions[3]
+            .1
+            .starts_with("conditions:\n  - lastProbeTime")
+    );
+    assert!(expansions[3].1.ends_with("type: PodScheduled"));
+}
+
+#[test]
+fn test_quotes_and_brackets() {
+    let doc = document(r#"{"spec": {"containers": ["nginx:1.25", "envoy"]}}"#);
+    let expansions = expansions(&doc, 2, 0);
+    assert_eq!(
+        expansions,
+        vec![
+            (Kind::Token, r#"["nginx:1.25","#.to_string()),
+            (
+                Kind::Pair,
+                r#""containers": ["nginx:1.25", "envoy"]"#.to_string()
+            ),
+            (
+                Kind::Brackets,
+                r#"{"containers": ["nginx:1.25", "envoy"]}"#.to_string()
+            ),
+            (
+                Kind::Pair,
+                r#""spec": {"containers": ["nginx:1.25", "envoy"]}"#.to_string()
+            ),
+            (
+                Kind::Brackets,
+                r#"{"spec": {"containers": ["nginx:1.25", "envoy"]}}"#.to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_inside_quotes() {
+    let doc = document(r#"args: ["--port 8080", "-v"]"#);
+    let span = Span::new(Kind::Token, (0, 9), (0, 12));
+    let quoted = expand(&doc, &span).unwrap();
+    assert_eq!(text(&doc, &quoted), "--port 8080");
+    let quoted = expand(&doc, &quoted).unwrap();
+    assert_eq!(text(&doc, &quoted), "\"--port 8080\"");
+    let brackets = expand(&doc, &quoted).unwrap();
+    assert_eq!(text(&doc, &brackets), "\"--port 8080\", \"-v\"");
+}
+
+#[test]
+fn test_multi_row_brackets() {
+    let doc = document("{\n  \"a\": [\n    1,\n    2\n  ]\n}\n\nnext");
+    let expansions = expansions(&doc, 0, 2);
+    let texts: Vec<&str> = expansions.iter().map(|(_, text)| text.as_str()).collect();
+    assert_eq!(
+        texts,
+        vec![
+            "1,",
+            "1,\n    2",
+            "[\n    1,\n    2\n  ]",
+            "\"a\": [\n    1,\n    2\n  ]",
+            "{\n  \"a\": [\n    1,\n    2\n  ]\n}",
+        ]
+    );
+}
+
+#[test]
+fn test_paragraph_of_a_large_document() {
+    let doc = document(&format!("a b\nc d\n\n{}", "e\n".repeat(100_000)));
+    let span = token(&doc, 0, 0).unwrap();
+    let paragraph = std::iter::successors(Some(span), |span| expand(&doc, span))
+        .find(|span| span.kind == Kind::Paragraph)
+        .unwrap();
+    assert_eq!(text(&doc, &paragraph), "a b\nc d");
+    // Only the rows up to the end of the paragraph were looked for
+    assert!(!doc.rows_found().1);
+}
@@ -0,0 +1,49 @@
+use super::*;
+use crate::ansi;
+use crate::backend::Memory;
+
+#[test]
+fn test_draw_changed_lines() {
+    let memory = Memory::new(10, 5);
+    let output = memory.output();
+    let mut terminal = Terminal::new(memory).unwrap();
+    terminal.draw_line(0, "a").unwrap();
+    terminal.draw_line(1, "b").unwrap();
+    terminal.flush().unwrap();
+    output.borrow_mut().clear();
+
+    terminal.draw_line(0, "a").unwrap();
+    terminal.draw_line(1, "c").unwrap();
+    // Nothing goes out before the flush
+    assert_eq!(*output.borrow(), "");
+    terminal.flush().unwrap();
+    assert_eq!(
+        *output.borrow(),
+        format!("{}{}c", ansi::goto(0, 1), ansi::CLEAR_LINE)
+    );
+}
+
+#[test]
+fn test_redraw_after_clear() {
+    let memory = Memory::new(10, 5);
+    let output = memory.output();
+    let mut terminal = Terminal::new(memory).unwrap();
+    terminal.draw_line(0, "a").unwrap();
+    terminal.clear_screen().unwrap();
+    terminal.draw_line(0, "a").unwrap();
+    terminal.flush().unwrap();
+    assert!(output.borrow().ends_with('a'));
+
+    terminal.writeln("Copied:").unwrap();
+    terminal.draw_line(0, "b").unwrap();
+    terminal.draw_line(0, "b").unwrap();
+    terminal.flush().unwrap();
+    assert_eq!(output.borrow().matches('b').count(), 1);
+}
+
+#[test]
+fn test_size() {
+    let terminal = Terminal::new(Memory::new(80, 24)).unwrap();
+    // Without the status and message bars
+    assert_eq!((terminal.size().width, terminal.size().height), (80, 22));
+}
@@ -0,0 +1,57 @@
+use super::*;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+
+// Puts an executable `tmux` script in a fresh directory, to be used as `PATH`.
+fn fake_tmux(name: &str, script: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("lcp-{name}-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let tmux = dir.join("tmux");
+    fs::write(&tmux, format!("#!/bin/sh\n{script}\n")).unwrap();
+    fs::set_permissions(&tmux, fs::Permissions::from_mode(0o755)).unwrap();
+    dir
+}
+
+#[test]
+fn test_capture_current_pane() {
+    let dir = fake_tmux("capture", r#"echo "$@""#);
+    let tmux = Tmux::with_path(&dir);
+    assert_eq!(
+        tmux.capture_pane(None).unwrap(),
+        b"capture-pane -p -J -S - -E -\n"
+    );
+}
+
+#[test]
+fn test_capture_pane_by_id() {
+    let dir = fake_tmux("capture-id", r#"echo "$@""#);
+    let tmux = Tmux::with_path(&dir);
+    assert_eq!(
+        tmux.capture_pane(Some("%3")).unwrap(),
+        b"capture-pane -p -J -S - -E - -t %3\n"
+    );
+}
+
+#[test]
+fn test_set_buffer() {
+    let buffer = std::env::temp_dir().join(format!("lcp-buffer-{}", std::process::id()));
+    let dir = fake_tmux(
+        "set-buffer",
+        &format!(r#"printf '%s' "$3" > {}"#, buffer.display()),
+    );
+    let tmux = Tmux::with_path(&dir);
+    tmux.set_buffer("foo bar\nbaz").unwrap();
+    assert_eq!(fs::read_to_string(buffer).unwrap(), "foo bar\nbaz");
+}
+
+#[test]
+fn test_failure() {
+    let dir = fake_tmux("failure", "echo 'no server running' >&2; exit 1");
+    let tmux = Tmux::with_path(&dir);
+    let err = tmux.capture_pane(None).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "tmux capture-pane failed: no server running"
+    );
+}
@@ -0,0 +1,119 @@
+use super::*;
+
+fn parse(s: &str) -> Option<Tree> {
+    Tree::parse(s.lines())
+}
+
+/// The path and value of the node at `(y, x)`.
+fn at(tree: &Tree, y: usize, x: usize) -> (String, String) {
+    let node = tree.node_at(y, x);
+    (tree.path(node), tree.value(node))
+}
+
+#[test]
+fn test_yaml_paths() {
+    let tree = parse(include_str!("files/pod.yaml")).unwrap();
+    assert_eq!(
+        at(&tree, 4, 4),
+        (
+            r#".metadata.annotations."cluster-autoscaler.kubernetes.io/safe-to-evict""#.to_string(),
+            "false".to_string()
+        )
+    );
+    assert_eq!(
+        at(&tree, 22, 26),
+        (
+            ".status.conditions[2].lastTransitionTime".to_string(),
+            "2023-09-18T21:16:11Z".to_string()
+        )
+    );
+    assert_eq!(
+        at(&tree, 35, 10),
+        (
+            ".status.containerStatuses[0].name".to_string(),
+            "logdb-shared".to_string()
+        )
+    );
+    assert_eq!(tree.path(0), ".");
+}
+
+#[test]
+fn test_yaml_subtree() {
+    let tree = parse(include_str!("files/pod.yaml")).unwrap();
+    let conditions = tree.node(tree.node_at(12, 4)).parent.unwrap();
+    assert_eq!(tree.path(conditions), ".status.conditions[0]");
+    assert_eq!(
+        tree.serialize(conditions),
+        "lastProbeTime: null
+lastTransitionTime: null
+reason: LoadBalancerNegWithoutHealthCheck
+status: \"True\"
+type: cloud.google.com/load-balancer-neg-ready"
+    );
+    let node = tree.node(conditions);
+    assert_eq!((node.start, node.end), ((12, 4), (16, 49)));
+}
+
+#[test]
+fn test_json() {
+    let tree = parse("{\n  \"items\": [\n    {\"name\": \"a b\", \"ports\": [80, 443]},\n    {\"name\": null}\n  ]\n}").unwrap();
+    assert_eq!(
+        at(&tree, 2, 15),
+        (".items[0].name".to_string(), "a b".to_string())
+    );
+    assert_eq!(
+        at(&tree, 2, 36),
+        (".items[0].ports[1]".to_string(), "443".to_string())
+    );
+    let first = tree.node(tree.node_at(2, 15)).parent.unwrap();
+    assert_eq!(
+        tree.serialize(first),
+        "{\n  \"name\": \"a b\",\n  \"ports\": [\n    80,\n    443\n  ]\n}"
+    );
+    assert_eq!(tree.serialize(tree.node_at(3, 14)), "null");
+}
+
+#[test]
+fn test_yaml_collections() {
+    let tree = parse(
+        "spec:\n  containers:\n  - name: web\n    args: [\"--port\", '8080']\n    script: |\n      echo hi\n      exit 1\n  - - nested\n",
+    )
+    .unwrap();
+    assert_eq!(
+        at(&tree, 3, 22),
+        (
+            ".spec.containers[0].args[1]".to_string(),
+            "8080".to_string()
+        )
+    );
+    assert_eq!(
+        at(&tree, 5, 8),
+        (
+            ".spec.containers[0].script".to_string(),
+            "echo hi\nexit 1".to_string()
+        )
+    );
+    assert_eq!(
+        at(&tree, 7, 6),
+        (".spec.containers[1][0]".to_string(), "nested".to_string())
+    );
+    assert_eq!(
+        tree.serialize(tree.node(tree.node_at(2, 10)).parent.unwrap()),
+        "name: web\nargs:\n  - \"--port\"\n  - \"8080\"\nscript: \"echo hi\\nexit 1\""
+    );
+}
+
+#[test]
+fn test_not_a_tree() {
+    assert!(parse(include_str!("files/k-get-po.txt")).is_none());
+    assert!(parse(include_str!("files/git-status.txt")).is_none());
+    assert!(parse("just: a\n  stray indent").is_none());
+    assert!(parse("{\"unterminated\": [1, 2}").is_none());
+    assert!(parse("").is_none());
+}
+
+#[test]
+fn test_empty_key() {
+    assert!(parse(":").is_none());
+    assert!(parse("a: 1\n: b").is_none());
+}
@@ -0,0 +1,60 @@
+use std::ffi::OsString;
+use std::io;
+use std::process::{Command, Output};
+
+/// Thin wrapper around the `tmux` binary.
+#[derive(Debug, Default)]
+pub struct Tmux {
+    // Overrides the `PATH` used to look up `tmux`. Only used in tests.
+    path: Option<OsString>,
+}
+
+impl Tmux {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn with_path(path: impl Into<OsString>) -> Self {
+        Self {
+            path: Some(path.into()),
+        }
+    }
+
+    fn run(&self, args: &[&str]) -> io::Result<Output> {
+        let mut command = Command::new("tmux");
+        if let Some(path) = &self.path {
+            command.env("PATH", path);
+        }
+        let output = command.args(args).output()?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(io::Error::other(format!(
+                "tmux {} failed: {}",
+                args.first().unwrap_or(&""),
+                stderr.trim()
+            )));
+        }
+        Ok(output)
+    }
+
+    /// Returns the contents of `pane`, or of the current pane when `None`,
+    /// from the start of its scrollback to the end of the screen. Wrapped
+    /// lines are joined so tokens aren't split at the pane width.
+    pub(crate) fn capture_pane(&self, pane: Option<&str>) -> io::Result<Vec<u8>> {
+        let mut args = vec!["capture-pane", "-p", "-J", "-S", "-", "-E", "-"];
+        if let Some(pane) = pane {
+            args.extend(["-t", pane]);
+        }
+        Ok(self.run(&args)?.stdout)
+    }
+
+    pub(crate) fn set_buffer(&self, text: &str) -> io::Result<()> {
+        self.run(&["set-buffer", "--", text])?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/test_tmux.rs"]
+mod tests;
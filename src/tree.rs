@@ -0,0 +1,746 @@
+use crate::output::json_string;
+
+/// The syntax the document was parsed from, which subtrees are written back in.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Format {
+    Yaml,
+    Json,
+}
+
+/// How a node is reached from its parent.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Value {
+    /// The decoded text, and whether it was quoted.
+    Scalar(String, bool),
+    Sequence,
+    Mapping,
+}
+
+/// A value of the tree and where it is in the document, as `(y, x)` of its
+/// first and last characters.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Node {
+    pub(crate) parent: Option<usize>,
+    pub(crate) segment: Option<Segment>,
+    pub(crate) children: Vec<usize>,
+    pub(crate) value: Value,
+    pub(crate) start: (usize, usize),
+    pub(crate) end: (usize, usize),
+    /// Where the key is, for values of a mapping.
+    pub(crate) key: Option<((usize, usize), (usize, usize))>,
+}
+
+/// A YAML or JSON document whose root is a mapping or a sequence.
+///
+/// Only the common subset of YAML is understood: block mappings and
+/// sequences, flow collections, quoted and plain scalars and block scalars.
+/// Anchors, tags and multi-line plain scalars make the document not parse.
+#[derive(Debug)]
+pub struct Tree {
+    /// In pre-order, so the root is first and parents come before children.
+    nodes: Vec<Node>,
+    format: Format,
+}
+
+impl Tree {
+    pub(crate) fn parse<'a>(lines: impl IntoIterator<Item = &'a str>) -> Option<Self> {
+        let mut parser = Parser {
+            lines: lines.into_iter().collect(),
+            nodes: Vec::new(),
+        };
+        let y = parser.next_content_line(0)?;
+        let x = indent(parser.lines[y]);
+        let format = match parser.lines[y].as_bytes()[x] {
+            b'{' | b'[' => {
+                let mut position = (y, x);
+                parser.flow(&mut position, None, None, None)?;
+                // Nothing but whitespace may follow
+                if parser.skip_whitespace(&mut position).is_some() {
+                    return None;
+                }
+                Format::Json
+            }
+            _ => {
+                let next = parser.block(y, x, None, None, None)?;
+                if parser.next_content_line(next).is_some() {
+                    return None;
+                }
+                Format::Yaml
+            }
+        };
+        if let Value::Scalar(..) = parser.nodes[0].value {
+            return None;
+        }
+        Some(Self {
+            nodes: parser.nodes,
+            format,
+        })
+    }
+
+    pub(crate) fn node(&self, index: usize) -> &Node {
+        &self.nodes[index]
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// The innermost node at `(y, x)`, counting a key as part of its value.
+    /// Falls back to the first node on row `y`, then to the root.
+    pub(crate) fn node_at(&self, y: usize, x: usize) -> usize {
+        let contains =
+            |(start, end): ((usize, usize), (usize, usize))| start <= (y, x) && (y, x) <= end;
+        self.nodes
+            .iter()
+            .rposition(|node| contains((node.start, node.end)) || node.key.is_some_and(contains))
+            .or_else(|| self.nodes.iter().position(|node| node.start.0 == y))
+            .unwrap_or(0)
+    }
+
+    /// The path to a node, like `.spec.containers[0].image`.
+    pub(crate) fn path(&self, index: usize) -> String {
+        let mut segments = Vec::new();
+        let mut node = &self.nodes[index];
+        while let Some(segment) = &node.segment {
+            segments.push(match segment {
+                Segment::Key(key) if is_identifier(key) => format!(".{key}"),
+                Segment::Key(key) => format!(".{}", json_string(key)),
+                Segment::Index(i) => format!("[{i}]"),
+            });
+            node = &self.nodes[node.parent.unwrap_or_default()];
+        }
+        segments.reverse();
+        let path = segments.concat();
+        if path.starts_with('.') {
+            path
+        } else {
+            format!(".{path}")
+        }
+    }
+
+    /// The text of a scalar, or a collection written back in the document's format.
+    pub(crate) fn value(&self, index: usize) -> String {
+        match &self.nodes[index].value {
+            Value::Scalar(text, _) => text.clone(),
+            _ => self.serialize(index),
+        }
+    }
+
+    /// A node written back in the document's format.
+    pub(crate) fn serialize(&self, index: usize) -> String {
+        let mut result = String::new();
+        match self.format {
+            Format::Json => self.json(index, 0, &mut result),
+            Format::Yaml => {
+                self.yaml(index, 0, &mut result);
+                // Every line of a collection ends with a newline
+                if result.ends_with('\n') {
+                    result.pop();
+                }
+            }
+        }
+        result
+    }
+
+    fn json(&self, index: usize, indent: usize, result: &mut String) {
+        let node = &self.nodes[index];
+        let pad = " ".repeat(indent + 2);
+        let (open, close) = match node.value {
+            Value::Scalar(ref text, quoted) => {
+                result.push_str(&json_scalar(text, quoted));
+                return;
+            }
+            Value::Sequence => ('[', ']'),
+            Value::Mapping => ('{', '}'),
+        };
+        result.push(open);
+        for (i, &child) in node.children.iter().enumerate() {
+            result.push_str(if i == 0 { "\n" } else { ",\n" });
+            result.push_str(&pad);
+            if let Some(Segment::Key(key)) = &self.nodes[child].segment {
+                result.push_str(&json_string(key));
+                result.push_str(": ");
+            }
+            self.json(child, indent + 2, result);
+        }
+        if !node.children.is_empty() {
+            result.push('\n');
+            result.push_str(&" ".repeat(indent));
+        }
+        result.push(close);
+    }
+
+    /// Writes a node as block YAML at `indent`. Collections end with a newline.
+    fn yaml(&self, index: usize, indent: usize, result: &mut String) {
+        let node = &self.nodes[index];
+        let pad = " ".repeat(indent);
+        match &node.value {
+            Value::Scalar(text, quoted) => result.push_str(&yaml_scalar(text, *quoted)),
+            _ if node.children.is_empty() => result.push_str(if node.value == Value::Mapping {
+                "{}"
+            } else {
+                "[]"
+            }),
+            Value::Mapping => {
+                for &child in &node.children {
+                    let key = match &self.nodes[child].segment {
+                        Some(Segment::Key(key)) => key.as_str(),
+                        _ => "",
+                    };
+                    result.push_str(&pad);
+                    result.push_str(&yaml_scalar(key, !is_plain(key)));
+                    result.push(':');
+                    self.yaml_child(child, indent + 2, result);
+                }
+            }
+            Value::Sequence => {
+                for &child in &node.children {
+                    // A collection in a sequence starts on the line of its dash
+                    let mut item = String::new();
+                    self.yaml_child(child, indent + 2, &mut item);
+                    result.push_str(&pad);
+                    result.push('-');
+                    match item.strip_prefix('\n') {
+                        Some(collection) => {
+                            result.push(' ');
+                            result.push_str(&collection[indent + 2..]);
+                        }
+                        None => result.push_str(&item),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Writes the value of a key or item, after its `:` or `-`.
+    fn yaml_child(&self, index: usize, indent: usize, result: &mut String) {
+        let node = &self.nodes[index];
+        if matches!(node.value, Value::Scalar(..)) || node.children.is_empty() {
+            result.push(' ');
+            self.yaml(index, indent, result);
+            result.push('\n');
+        } else {
+            result.push('\n');
+            self.yaml(index, indent, result);
+        }
+    }
+}
+
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Whether YAML reads `text` back as the same string when unquoted.
+fn is_plain(text: &str) -> bool {
+    !text.is_empty()
+        && text.trim() == text
+        && !text.starts_with(|c: char| "-?:,[]{}#&*!|>'\"%@`".contains(c))
+        && !text.contains(": ")
+        && !text.contains(" #")
+        && !text.ends_with(':')
+        && !text.contains(|c: char| c.is_control())
+}
+
+/// Quoted scalars stay quoted, as YAML may read them as another type otherwise.
+fn yaml_scalar(text: &str, quoted: bool) -> String {
+    if quoted || !is_plain(text) {
+        json_string(text)
+    } else {
+        text.to_string()
+    }
+}
+
+fn json_scalar(text: &str, quoted: bool) -> String {
+    if quoted {
+        return json_string(text);
+    }
+    match text {
+        "" | "~" | "null" | "Null" | "NULL" => "null".to_string(),
+        "true" | "True" | "TRUE" => "true".to_string(),
+        "false" | "False" | "FALSE" => "false".to_string(),
+        // JSON numbers are a subset of what parses as f64
+        _ if text.parse::<f64>().is_ok_and(f64::is_finite)
+            && !text.starts_with(['+', '.'])
+            && !text.ends_with('.') =>
+        {
+            text.to_string()
+        }
+        _ => json_string(text),
+    }
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn is_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+/// Where the `:` of a `key: value` at the start of `text` is.
+fn key_colon(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let after_key = match bytes.first()? {
+        q @ (b'"' | b'\'') => 1 + bytes[1..].iter().position(|b| b == q)? + 1,
+        b'-' | b'[' | b'{' | b'#' | b'|' | b'>' => return None,
+        _ => 0,
+    };
+    let colon = after_key
+        + text[after_key..]
+            .match_indices(':')
+            .map(|(i, _)| i)
+            .find(|&i| matches!(bytes.get(after_key + i + 1), None | Some(b' ')))?;
+    // A quoted key must be followed by its colon, and there must be a key
+    if after_key > 0 && text[after_key..colon].trim() != "" || text[..colon].trim().is_empty() {
+        return None;
+    }
+    Some(colon)
+}
+
+/// Decodes a quoted scalar, given with its quotes.
+fn unquote(raw: &str) -> String {
+    let inner = &raw[1..raw.len() - 1];
+    if raw.starts_with('\'') {
+        return inner.replace("''", "'");
+    }
+    let mut result = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some('0') => result.push('\0'),
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                if let Some(c) = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    result.push(c);
+                }
+            }
+            Some(c) => result.push(c),
+            None => {}
+        }
+    }
+    result
+}
+
+/// The length of the quoted string at the start of `text`, quotes included.
+fn quoted_len(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let quote = bytes[0];
+    let mut i = 1;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && quote == b'"' {
+            i += 2;
+            continue;
+        }
+        if bytes[i] == quote {
+            // '' is an escaped quote in single quoted strings
+            if quote == b'\'' && bytes.get(i + 1) == Some(&b'\'') {
+                i += 2;
+                continue;
+            }
+            return Some(i + 1);
+        }
+        i += 1;
+    }
+    None
+}
+
+/// `text` without a trailing ` # comment`.
+fn strip_comment(text: &str) -> &str {
+    match text.find(" #") {
+        Some(i) => text[..i].trim_end(),
+        None => text,
+    }
+}
+
+struct Parser<'a> {
+    lines: Vec<&'a str>,
+    nodes: Vec<Node>,
+}
+
+impl Parser<'_> {
+    fn push(
+        &mut self,
+        value: Value,
+        start: (usize, usize),
+        parent: Option<usize>,
+        segment: Option<Segment>,
+        key: Option<((usize, usize), (usize, usize))>,
+    ) -> usize {
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            parent,
+            segment,
+            children: Vec::new(),
+            value,
+            start,
+            end: start,
+            key,
+        });
+        if let Some(parent) = parent {
+            self.nodes[parent].children.push(index);
+        }
+        index
+    }
+
+    fn is_skipped(&self, y: usize) -> bool {
+        let line = self.lines[y];
+        let text = line.trim();
+        text.is_empty() || text.starts_with('#') || line.starts_with("---") || line == "..."
+    }
+
+    fn next_content_line(&self, y: usize) -> Option<usize> {
+        (y..self.lines.len()).find(|&y| !self.is_skipped(y))
+    }
+
+    /// Parses the block node starting at `(y, x)`. Returns the row after it.
+    fn block(
+        &mut self,
+        y: usize,
+        x: usize,
+        parent: Option<usize>,
+        segment: Option<Segment>,
+        key: Option<((usize, usize), (usize, usize))>,
+    ) -> Option<usize> {
+        let text = &self.lines[y][x..];
+        if is_item(text) {
+            self.sequence(y, x, parent, segment, key)
+        } else if key_colon(text).is_some() {
+            self.mapping(y, x, parent, segment, key)
+        } else {
+            self.inline(y, x, indent(self.lines[y]), parent, segment, key)
+        }
+    }
+
+    /// Parses a value that starts on row `y` after a key or a dash: a scalar,
+    /// a flow collection or a block scalar. `indent` is the indentation of
+    /// the row the value belongs to.
+    fn inline(
+        &mut self,
+        y: usize,
+        x: usize,
+        indent: usize,
+        parent: Option<usize>,
+        segment: Option<Segment>,
+        key: Option<((usize, usize), (usize, usize))>,
+    ) -> Option<usize> {
+        let text = strip_comment(&self.lines[y][x..]);
+        match text.as_bytes()[0] {
+            b'{' | b'[' => {
+                let mut position = (y, x);
+                self.flow(&mut position, parent, segment, key)?;
+                let (end_y, end_x) = position;
+                if !strip_comment(&self.lines[end_y][end_x..]).trim().is_empty() {
+                    return None;
+                }
+                Some(end_y + 1)
+            }
+            b'|' | b'>' => self.block_scalar(y, x, indent, parent, segment, key),
+            b'&' | b'*' | b'!' => None,
+            b'"' | b'\'' => {
+                let len = quoted_len(text)?;
+                if len != text.len() {
+                    return None;
+                }
+                let node = self.push(
+                    Value::Scalar(unquote(text), true),
+                    (y, x),
+                    parent,
+                    segment,
+                    key,
+                );
+                self.nodes[node].end = (y, x + len - 1);
+                Some(y + 1)
+            }
+            _ => {
+                let node = self.push(
+                    Value::Scalar(text.to_string(), false),
+                    (y, x),
+                    parent,
+                    segment,
+                    key,
+                );
+                self.nodes[node].end = (y, x + text.len() - 1);
+                Some(y + 1)
+            }
+        }
+    }
+
+    fn block_scalar(
+        &mut self,
+        y: usize,
+        x: usize,
+        indent: usize,
+        parent: Option<usize>,
+        segment: Option<Segment>,
+        key: Option<((usize, usize), (usize, usize))>,
+    ) -> Option<usize> {
+        let folded = self.lines[y].as_bytes()[x] == b'>';
+        let mut end = y;
+        for next in y + 1..self.lines.len() {
+            let line = self.lines[next];
+            if line.trim().is_empty() {
+                continue;
+            }
+            if self::indent(line) <= indent {
+                break;
+            }
+            end = next;
+        }
+        let content_indent = (y + 1..=end)
+            .map(|y| self.lines[y])
+            .filter(|line| !line.trim().is_empty())
+            .map(self::indent)
+            .min()
+            .unwrap_or_default();
+        let lines: Vec<&str> = (y + 1..=end)
+            .map(|y| self.lines[y].get(content_indent..).unwrap_or(""))
+            .collect();
+        let text = lines.join(if folded { " " } else { "\n" });
+        let node = self.push(Value::Scalar(text, true), (y, x), parent, segment, key);
+        self.nodes[node].end = (end, self.lines[end].len().saturating_sub(1));
+        Some(end + 1)
+    }
+
+    fn mapping(
+        &mut self,
+        y: usize,
+        x: usize,
+        parent: Option<usize>,
+        segment: Option<Segment>,
+        key: Option<((usize, usize), (usize, usize))>,
+    ) -> Option<usize> {
+        let node = self.push(Value::Mapping, (y, x), parent, segment, key);
+        let mut y = y;
+        loop {
+            let text = &self.lines[y][x..];
+            let colon = key_colon(text)?;
+            let raw_key = text[..colon].trim_end();
+            let key_name = if raw_key.starts_with(['"', '\'']) {
+                unquote(raw_key)
+            } else {
+                raw_key.to_string()
+            };
+            let key_span = ((y, x), (y, x + raw_key.len() - 1));
+            let segment = Some(Segment::Key(key_name));
+            let rest = &text[colon + 1..];
+            let value_x = x + colon + 1 + indent(rest);
+
+            let next = if strip_comment(rest).trim().is_empty() {
+                let line_indent = indent(self.lines[y]);
+                match self.next_content_line(y + 1) {
+                    Some(next)
+                        if indent(self.lines[next]) > line_indent
+                            || (indent(self.lines[next]) == line_indent
+                                && is_item(self.lines[next].trim_start())) =>
+                    {
+                        let next_x = indent(self.lines[next]);
+                        self.block(next, next_x, Some(node), segment, Some(key_span))?
+                    }
+                    _ => {
+                        let value = self.push(
+                            Value::Scalar(String::new(), false),
+                            key_span.0,
+                            Some(node),
+                            segment,
+                            Some(key_span),
+                        );
+                        self.nodes[value].end = key_span.1;
+                        y + 1
+                    }
+                }
+            } else {
+                self.inline(y, value_x, x, Some(node), segment, Some(key_span))?
+            };
+            self.nodes[node].end = self.nodes[self.nodes.len() - 1].end;
+            let Some(next) = self.next_content_line(next) else {
+                return Some(self.lines.len());
+            };
+            let next_indent = indent(self.lines[next]);
+            if next_indent < x {
+                return Some(next);
+            }
+            if next_indent > x {
+                return None;
+            }
+            if is_item(&self.lines[next][x..]) {
+                // The next item of the sequence this mapping is in
+                return Some(next);
+            }
+            y = next;
+        }
+    }
+
+    fn sequence(
+        &mut self,
+        y: usize,
+        x: usize,
+        parent: Option<usize>,
+        segment: Option<Segment>,
+        key: Option<((usize, usize), (usize, usize))>,
+    ) -> Option<usize> {
+        let node = self.push(Value::Sequence, (y, x), parent, segment, key);
+        let mut y = y;
+        for index in 0.. {
+            let segment = Some(Segment::Index(index));
+            let rest = &self.lines[y][x + 1..];
+            let next = if rest.trim().is_empty() {
+                match self.next_content_line(y + 1) {
+                    Some(next) if indent(self.lines[next]) > x => {
+                        let next_x = indent(self.lines[next]);
+                        self.block(next, next_x, Some(node), segment, None)?
+                    }
+                    _ => {
+                        let value = self.push(
+                            Value::Scalar(String::new(), false),
+                            (y, x),
+                            Some(node),
+                            segment,
+                            None,
+                        );
+                        self.nodes[value].end = (y, x);
+                        y + 1
+                    }
+                }
+            } else {
+                let item_x = x + 1 + indent(rest);
+                let text = &self.lines[y][item_x..];
+                if is_item(text) || key_colon(text).is_some() {
+                    // A collection starting on the line of the dash
+                    self.block(y, item_x, Some(node), segment, None)?
+                } else {
+                    self.inline(y, item_x, x, Some(node), segment, None)?
+                }
+            };
+            self.nodes[node].end = self.nodes[self.nodes.len() - 1].end;
+            let Some(next) = self.next_content_line(next) else {
+                return Some(self.lines.len());
+            };
+            let next_indent = indent(self.lines[next]);
+            if next_indent != x || !is_item(&self.lines[next][x..]) {
+                if next_indent > x {
+                    return None;
+                }
+                return Some(next);
+            }
+            y = next;
+        }
+        None
+    }
+
+    /// Moves `position` to the next character that isn't whitespace and returns it.
+    fn skip_whitespace(&self, position: &mut (usize, usize)) -> Option<u8> {
+        loop {
+            let (y, x) = *position;
+            let line = self.lines.get(y)?.as_bytes();
+            match line.get(x) {
+                Some(b) if b.is_ascii_whitespace() => position.1 += 1,
+                Some(b) => return Some(*b),
+                None => *position = (y + 1, 0),
+            }
+        }
+    }
+
+    /// Parses a flow (JSON-like) value at `position` and moves past it.
+    fn flow(
+        &mut self,
+        position: &mut (usize, usize),
+        parent: Option<usize>,
+        segment: Option<Segment>,
+        key: Option<((usize, usize), (usize, usize))>,
+    ) -> Option<usize> {
+        let first = self.skip_whitespace(position)?;
+        let start = *position;
+        let node = match first {
+            b'{' | b'[' => {
+                let (value, close) = if first == b'{' {
+                    (Value::Mapping, b'}')
+                } else {
+                    (Value::Sequence, b']')
+                };
+                let node = self.push(value, start, parent, segment, key);
+                position.1 += 1;
+                for index in 0.. {
+                    if self.skip_whitespace(position)? == close {
+                        break;
+                    }
+                    if close == b']' {
+                        self.flow(position, Some(node), Some(Segment::Index(index)), None)?;
+                    } else {
+                        let key_start = *position;
+                        let (key_name, _) = self.flow_scalar(position, b":")?;
+                        let key_end = (position.0, position.1 - 1);
+                        if self.skip_whitespace(position)? != b':' {
+                            return None;
+                        }
+                        position.1 += 1;
+                        let segment = Some(Segment::Key(key_name));
+                        let key_span = Some((key_start, key_end));
+                        self.flow(position, Some(node), segment, key_span)?;
+                    }
+                    match self.skip_whitespace(position)? {
+                        b',' => position.1 += 1,
+                        b if b == close => break,
+                        _ => return None,
+                    }
+                }
+                node
+            }
+            b'}' | b']' | b',' | b':' => return None,
+            _ => {
+                let (text, quoted) = self.flow_scalar(position, b",]}")?;
+                self.push(Value::Scalar(text, quoted), start, parent, segment, key)
+            }
+        };
+        self.nodes[node].end = match first {
+            b'{' | b'[' => *position,
+            _ => (position.0, position.1 - 1),
+        };
+        if matches!(first, b'{' | b'[') {
+            position.1 += 1;
+        }
+        Some(node)
+    }
+
+    /// Reads a quoted scalar, or a plain one up to one of `stops` or the
+    /// end of the line, and moves `position` past it.
+    fn flow_scalar(&self, position: &mut (usize, usize), stops: &[u8]) -> Option<(String, bool)> {
+        let (y, x) = *position;
+        let text = &self.lines[y][x..];
+        if text.starts_with(['"', '\'']) {
+            let len = quoted_len(text)?;
+            position.1 += len;
+            return Some((unquote(&text[..len]), true));
+        }
+        let len = text
+            .bytes()
+            .position(|b| stops.contains(&b))
+            .unwrap_or(text.len());
+        let plain = text[..len].trim_end();
+        if plain.is_empty() {
+            return None;
+        }
+        position.1 += plain.len();
+        Some((plain.to_string(), false))
+    }
+}
+
+#[cfg(test)]
+#[path = "tests/test_tree.rs"]
+mod tests;